@@ -0,0 +1,147 @@
+use amm::events::{
+    AddToAllowlistEvent,
+    ClaimEvent,
+    ClosePoolEvent,
+    CollectProtocolFeesEvent,
+    CreateFarmEvent,
+    DepositEvent,
+    FlashLoanEvent,
+    FundFarmEvent,
+    RemoveFromAllowlistEvent,
+    SetObservationIntervalEvent,
+    StakeEvent,
+    SwapEvent,
+    UnstakeEvent,
+    UpdateConfigEvent,
+    UpdateLpMetadataEvent,
+    WithdrawEvent,
+};
+use anchor_lang::{ prelude::Pubkey, AnchorDeserialize, Discriminator };
+use base64::{ engine::general_purpose::STANDARD, Engine };
+
+const PROGRAM_DATA: &str = "Program data: ";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AmmEvent {
+    Deposit(DepositEvent),
+    Withdraw(WithdrawEvent),
+    Swap(SwapEvent),
+    FlashLoan(FlashLoanEvent),
+    CollectProtocolFees(CollectProtocolFeesEvent),
+    ClosePool(ClosePoolEvent),
+    CreateFarm(CreateFarmEvent),
+    FundFarm(FundFarmEvent),
+    Stake(StakeEvent),
+    Unstake(UnstakeEvent),
+    Claim(ClaimEvent),
+    UpdateConfig(UpdateConfigEvent),
+    AddToAllowlist(AddToAllowlistEvent),
+    RemoveFromAllowlist(RemoveFromAllowlistEvent),
+    UpdateLpMetadata(UpdateLpMetadataEvent),
+    SetObservationInterval(SetObservationIntervalEvent),
+}
+
+impl AmmEvent {
+    // the pool the event belongs to
+    pub fn config(&self) -> Pubkey {
+        match self {
+            AmmEvent::Deposit(event) => event.config,
+            AmmEvent::Withdraw(event) => event.config,
+            AmmEvent::Swap(event) => event.config,
+            AmmEvent::FlashLoan(event) => event.config,
+            AmmEvent::CollectProtocolFees(event) => event.config,
+            AmmEvent::ClosePool(event) => event.config,
+            AmmEvent::CreateFarm(event) => event.config,
+            AmmEvent::FundFarm(event) => event.config,
+            AmmEvent::Stake(event) => event.config,
+            AmmEvent::Unstake(event) => event.config,
+            AmmEvent::Claim(event) => event.config,
+            AmmEvent::UpdateConfig(event) => event.config,
+            AmmEvent::AddToAllowlist(event) => event.config,
+            AmmEvent::RemoveFromAllowlist(event) => event.config,
+            AmmEvent::UpdateLpMetadata(event) => event.config,
+            AmmEvent::SetObservationInterval(event) => event.config,
+        }
+    }
+}
+
+fn deserialize<T: AnchorDeserialize>(data: &[u8]) -> Option<T> {
+    T::deserialize(&mut &data[..]).ok()
+}
+
+// decodes one event: 8 byte discriminator followed by the borsh encoded struct
+pub fn decode_event(data: &[u8]) -> Option<AmmEvent> {
+    if data.len() < 8 {
+        return None;
+    }
+    let (discriminator, data) = data.split_at(8);
+    match discriminator {
+        d if d == DepositEvent::DISCRIMINATOR => deserialize(data).map(AmmEvent::Deposit),
+        d if d == WithdrawEvent::DISCRIMINATOR => deserialize(data).map(AmmEvent::Withdraw),
+        d if d == SwapEvent::DISCRIMINATOR => deserialize(data).map(AmmEvent::Swap),
+        d if d == FlashLoanEvent::DISCRIMINATOR => deserialize(data).map(AmmEvent::FlashLoan),
+        d if d == CollectProtocolFeesEvent::DISCRIMINATOR =>
+            deserialize(data).map(AmmEvent::CollectProtocolFees),
+        d if d == ClosePoolEvent::DISCRIMINATOR => deserialize(data).map(AmmEvent::ClosePool),
+        d if d == CreateFarmEvent::DISCRIMINATOR => deserialize(data).map(AmmEvent::CreateFarm),
+        d if d == FundFarmEvent::DISCRIMINATOR => deserialize(data).map(AmmEvent::FundFarm),
+        d if d == StakeEvent::DISCRIMINATOR => deserialize(data).map(AmmEvent::Stake),
+        d if d == UnstakeEvent::DISCRIMINATOR => deserialize(data).map(AmmEvent::Unstake),
+        d if d == ClaimEvent::DISCRIMINATOR => deserialize(data).map(AmmEvent::Claim),
+        d if d == UpdateConfigEvent::DISCRIMINATOR => deserialize(data).map(AmmEvent::UpdateConfig),
+        d if d == AddToAllowlistEvent::DISCRIMINATOR =>
+            deserialize(data).map(AmmEvent::AddToAllowlist),
+        d if d == RemoveFromAllowlistEvent::DISCRIMINATOR =>
+            deserialize(data).map(AmmEvent::RemoveFromAllowlist),
+        d if d == UpdateLpMetadataEvent::DISCRIMINATOR =>
+            deserialize(data).map(AmmEvent::UpdateLpMetadata),
+        d if d == SetObservationIntervalEvent::DISCRIMINATOR =>
+            deserialize(data).map(AmmEvent::SetObservationInterval),
+        _ => None,
+    }
+}
+
+// events emitted by the amm deployed at `amm::ID`, in emission order
+pub fn parse_logs<S: AsRef<str>>(logs: &[S]) -> Vec<AmmEvent> {
+    parse_program_logs(&amm::ID, logs)
+}
+
+// same as parse_logs for a deployment at another address. "Program data" lines are
+// attributed to whichever program is on top of the invoke stack, so data logged by other
+// programs, including ones the amm calls into, is skipped
+pub fn parse_program_logs<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Vec<AmmEvent> {
+    let program_id = program_id.to_string();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for log in logs {
+        let log = log.as_ref();
+        if let Some(data) = log.strip_prefix(PROGRAM_DATA) {
+            if stack.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+            if let Some(event) = STANDARD.decode(data).ok().and_then(|data| decode_event(&data)) {
+                events.push(event);
+            }
+            continue;
+        }
+
+        // "Program log: ...", "Program return: ..." and friends carry no stack changes
+        let mut words = log.split_whitespace();
+        if words.next() != Some("Program") {
+            continue;
+        }
+        let Some(id) = words.next().filter(|id| !id.ends_with(':')) else {
+            continue;
+        };
+        match words.next() {
+            Some("invoke") => stack.push(id),
+            Some(status) if status == "success" || status.starts_with("failed") => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    events
+}
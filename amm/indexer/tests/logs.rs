@@ -0,0 +1,264 @@
+// log vectors shaped like what an RPC node returns in a transaction's logMessages
+
+use amm::{
+    events::{
+        AddToAllowlistEvent,
+        ClaimEvent,
+        ClosePoolEvent,
+        CreateFarmEvent,
+        FundFarmEvent,
+        RemoveFromAllowlistEvent,
+        SetObservationIntervalEvent,
+        StakeEvent,
+        SwapEvent,
+        UnstakeEvent,
+        UpdateConfigEvent,
+        UpdateLpMetadataEvent,
+    },
+    state::{ AllowlistMode, DynamicFee, PriceBand },
+};
+use amm_indexer::{ decode_event, parse_logs, parse_program_logs, AmmEvent };
+use anchor_lang::{ prelude::Pubkey, Event };
+use base64::{ engine::general_purpose::STANDARD, Engine };
+
+const TOKEN: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const COMPUTE_BUDGET: &str = "ComputeBudget111111111111111111111111111111";
+
+fn swap_event(amount_in: u64) -> SwapEvent {
+    SwapEvent {
+        config: Pubkey::new_unique(),
+        user: Pubkey::new_unique(),
+        is_x: true,
+        amount_in,
+        amount_out: amount_in / 2,
+        fee: amount_in / 100,
+        referral_fee: 0,
+        reserve_x: 1_000_000,
+        reserve_y: 2_000_000,
+    }
+}
+
+fn data_line(event: &impl Event) -> String {
+    format!("Program data: {}", STANDARD.encode(event.data()))
+}
+
+fn amm() -> String {
+    amm::ID.to_string()
+}
+
+// an amm instruction at `depth` that moves tokens twice before emitting `data`
+fn amm_instruction(depth: usize, name: &str, data: &[String]) -> Vec<String> {
+    let mut logs = vec![
+        format!("Program {} invoke [{}]", amm(), depth),
+        format!("Program log: Instruction: {}", name),
+    ];
+    for _ in 0..2 {
+        logs.extend([
+            format!("Program {} invoke [{}]", TOKEN, depth + 1),
+            "Program log: Instruction: TransferChecked".to_string(),
+            format!("Program {} consumed 6200 of 180000 compute units", TOKEN),
+            format!("Program {} success", TOKEN),
+        ]);
+    }
+    logs.extend(data.iter().cloned());
+    logs.extend([
+        format!("Program {} consumed 41523 of 200000 compute units", amm()),
+        format!("Program {} success", amm()),
+    ]);
+    logs
+}
+
+#[test]
+fn decodes_events_after_nested_invokes() {
+    let (first, second) = (swap_event(1_000), swap_event(2_000));
+    let mut logs = vec![
+        format!("Program {} invoke [1]", COMPUTE_BUDGET),
+        format!("Program {} success", COMPUTE_BUDGET),
+    ];
+    logs.extend(amm_instruction(1, "Swap", &[data_line(&first)]));
+    logs.extend(amm_instruction(1, "Swap", &[data_line(&second)]));
+
+    assert_eq!(parse_logs(&logs), vec![AmmEvent::Swap(first), AmmEvent::Swap(second)]);
+}
+
+#[test]
+fn decodes_events_of_an_amm_called_by_another_program() {
+    let router = Pubkey::new_unique().to_string();
+    let event = swap_event(1_000);
+    let mut logs = vec![
+        format!("Program {} invoke [1]", router),
+        "Program log: Instruction: Route".to_string(),
+    ];
+    logs.extend(amm_instruction(2, "Swap", &[data_line(&event)]));
+    logs.extend([
+        format!("Program {} consumed 60211 of 200000 compute units", router),
+        format!("Program {} success", router),
+    ]);
+
+    assert_eq!(parse_logs(&logs), vec![AmmEvent::Swap(event)]);
+}
+
+#[test]
+fn skips_program_data_of_other_programs() {
+    // other programs may well log bytes that decode as amm events
+    let other = Pubkey::new_unique().to_string();
+    let spoofed = data_line(&swap_event(666));
+    let event = swap_event(1_000);
+    let mut logs = vec![
+        format!("Program {} invoke [1]", other),
+        spoofed.clone(),
+        format!("Program {} success", other),
+        format!("Program {} invoke [1]", amm()),
+        format!("Program {} invoke [2]", other),
+        spoofed.clone(),
+        format!("Program {} success", other),
+        data_line(&event),
+        format!("Program {} success", amm()),
+    ];
+    assert_eq!(parse_logs(&logs), vec![AmmEvent::Swap(event.clone())]);
+
+    // and the same amm binary at another address is another program
+    logs.push(spoofed);
+    assert_eq!(parse_program_logs(&Pubkey::new_unique(), &logs), vec![]);
+}
+
+#[test]
+fn keeps_what_was_logged_before_truncation() {
+    let event = swap_event(1_000);
+    let mut logs = amm_instruction(1, "Swap", &[data_line(&event)]);
+    logs.extend([
+        format!("Program {} invoke [1]", amm()),
+        "Program log: Instruction: Swap".to_string(),
+        format!("Program {} invoke [2]", TOKEN),
+        "Log truncated".to_string(),
+    ]);
+    assert_eq!(parse_logs(&logs), vec![AmmEvent::Swap(event)]);
+
+    // cut off in the middle of a data line
+    let mut logs = amm_instruction(1, "Swap", &[]);
+    logs.insert(logs.len() - 2, data_line(&swap_event(1_000))[..40].to_string());
+    assert_eq!(parse_logs(&logs), vec![]);
+}
+
+#[test]
+fn failed_instructions_leave_the_stack_balanced() {
+    let event = swap_event(1_000);
+    let mut logs = vec![
+        format!("Program {} invoke [1]", amm()),
+        format!("Program {} invoke [2]", TOKEN),
+        format!("Program {} failed: insufficient funds", TOKEN),
+        format!("Program {} failed: custom program error: 0x1", amm()),
+    ];
+    logs.extend(amm_instruction(1, "Swap", &[data_line(&event)]));
+    assert_eq!(parse_logs(&logs), vec![AmmEvent::Swap(event)]);
+}
+
+#[test]
+fn ignores_data_that_is_not_an_amm_event() {
+    let logs = amm_instruction(1, "Swap", &[
+        "Program data: not base64 at all".to_string(),
+        format!("Program data: {}", STANDARD.encode([1u8; 4])),
+        format!("Program data: {}", STANDARD.encode([7u8; 64])),
+        "Program return: 11111111111111111111111111111111 AQ==".to_string(),
+    ]);
+    assert_eq!(parse_logs(&logs), vec![]);
+}
+
+#[test]
+fn decodes_close_pool_and_farm_events() {
+    let (config, farm, user) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let events = [
+        AmmEvent::ClosePool(ClosePoolEvent { config, authority: user, amount_x: 7, amount_y: 9 }),
+        AmmEvent::CreateFarm(CreateFarmEvent {
+            config,
+            farm,
+            reward_mint: Pubkey::new_unique(),
+            reward_rate: 1_000,
+        }),
+        AmmEvent::FundFarm(FundFarmEvent {
+            config,
+            farm,
+            funder: user,
+            amount: 50_000,
+            undistributed: 80_000,
+        }),
+        AmmEvent::Stake(StakeEvent { config, farm, user, amount: 10, total_staked: 30 }),
+        AmmEvent::Unstake(UnstakeEvent { config, farm, user, amount: 10, total_staked: 20 }),
+        AmmEvent::Claim(ClaimEvent { config, farm, user, amount: 123 }),
+    ];
+    for event in events {
+        let data = match &event {
+            AmmEvent::ClosePool(event) => event.data(),
+            AmmEvent::CreateFarm(event) => event.data(),
+            AmmEvent::FundFarm(event) => event.data(),
+            AmmEvent::Stake(event) => event.data(),
+            AmmEvent::Unstake(event) => event.data(),
+            AmmEvent::Claim(event) => event.data(),
+            _ => unreachable!(),
+        };
+        assert_eq!(event.config(), config);
+        assert_eq!(decode_event(&data), Some(event));
+    }
+}
+
+#[test]
+fn decodes_admin_events() {
+    let (config, user) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let events = [
+        AmmEvent::UpdateConfig(UpdateConfigEvent {
+            config,
+            authority: Some(Pubkey::new_unique()),
+            fee: 30,
+            dynamic_fee: Some(DynamicFee {
+                min_fee: 10,
+                max_fee: 100,
+                sensitivity: 5_000,
+                decay_period: 600,
+                volatility: 42,
+                last_update: 1_700_000_000,
+            }),
+            protocol_fee: 1_000,
+            referral_fee: 500,
+            allowlist: Some(AllowlistMode { deposit: true, swap: false }),
+            price_band: Some(PriceBand { reference: Pubkey::new_unique(), max_deviation: 200 }),
+            locked: true,
+        }),
+        AmmEvent::UpdateConfig(UpdateConfigEvent {
+            config,
+            authority: None,
+            fee: 30,
+            dynamic_fee: None,
+            protocol_fee: 0,
+            referral_fee: 0,
+            allowlist: None,
+            price_band: None,
+            locked: false,
+        }),
+        AmmEvent::AddToAllowlist(AddToAllowlistEvent {
+            config,
+            user,
+            can_deposit: true,
+            can_swap: false,
+        }),
+        AmmEvent::RemoveFromAllowlist(RemoveFromAllowlistEvent { config, user }),
+        AmmEvent::UpdateLpMetadata(UpdateLpMetadataEvent {
+            config,
+            name: "Pool LP".to_string(),
+            symbol: "PLP".to_string(),
+            uri: "https://example.com/lp.json".to_string(),
+        }),
+        AmmEvent::SetObservationInterval(SetObservationIntervalEvent { config, interval: 60 }),
+    ];
+    for event in events {
+        let data = match &event {
+            AmmEvent::UpdateConfig(event) => event.data(),
+            AmmEvent::AddToAllowlist(event) => event.data(),
+            AmmEvent::RemoveFromAllowlist(event) => event.data(),
+            AmmEvent::UpdateLpMetadata(event) => event.data(),
+            AmmEvent::SetObservationInterval(event) => event.data(),
+            _ => unreachable!(),
+        };
+        assert_eq!(event.config(), config);
+        assert_eq!(decode_event(&data), Some(event));
+    }
+}
@@ -0,0 +1,9 @@
+// helpers for programs and clients talking to the amm: address derivation, and with the
+// `cpi` feature, wrappers that fill in the pool side of swap, deposit and withdraw
+mod pda;
+#[cfg(feature = "cpi")]
+mod pool;
+
+pub use pda::*;
+#[cfg(feature = "cpi")]
+pub use pool::*;
@@ -0,0 +1,131 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+
+// each returns the address and bump the program itself derives
+
+pub fn registry_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"registry"], &crate::ID)
+}
+
+pub fn config_address(seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config", seed.to_le_bytes().as_ref()], &crate::ID)
+}
+
+pub fn lp_mint_address(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"lp", config.as_ref()], &crate::ID)
+}
+
+pub fn oracle_address(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"oracle", config.as_ref()], &crate::ID)
+}
+
+// mint_x < mint_y, `fee` is the creation fee tier, not whatever update_fee has set since
+pub fn pool_entry_address(mint_x: &Pubkey, mint_y: &Pubkey, fee: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"pool", mint_x.as_ref(), mint_y.as_ref(), fee.to_le_bytes().as_ref()],
+        &crate::ID
+    )
+}
+
+// the `index`th registered pool, for index in 0..registry.pool_count
+pub fn pool_index_address(index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool", index.to_le_bytes().as_ref()], &crate::ID)
+}
+
+pub fn allowlist_entry_address(config: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"allowlist", config.as_ref(), user.as_ref()], &crate::ID)
+}
+
+pub fn farm_address(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"farm", config.as_ref()], &crate::ID)
+}
+
+pub fn user_stake_address(farm: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stake", farm.as_ref(), user.as_ref()], &crate::ID)
+}
+
+// the config's associated token account for `mint`, vault_x and vault_y are these
+pub fn vault_address(config: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(config, mint, token_program)
+}
+
+// where the first deposit's MINIMUM_LIQUIDITY sits; the lp mint is always under `token_program`
+pub fn locked_lp_address(config: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(config, &lp_mint_address(config).0, token_program)
+}
+
+// every pool-owned address an instruction may need
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolAddresses {
+    pub config: Pubkey,
+    pub mint_lp: Pubkey,
+    pub oracle: Pubkey,
+    pub vault_x: Pubkey,
+    pub vault_y: Pubkey,
+    pub locked_lp: Pubkey,
+}
+
+impl PoolAddresses {
+    pub fn new(
+        seed: u64,
+        mint_x: &Pubkey,
+        mint_y: &Pubkey,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
+        token_program: &Pubkey
+    ) -> Self {
+        let config = config_address(seed).0;
+        PoolAddresses {
+            config,
+            mint_lp: lp_mint_address(&config).0,
+            oracle: oracle_address(&config).0,
+            vault_x: vault_address(&config, mint_x, token_program_x),
+            vault_y: vault_address(&config, mint_y, token_program_y),
+            locked_lp: locked_lp_address(&config, token_program),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the seeds as the instructions spell them, checked with the returned bump
+    fn assert_derived(address: (Pubkey, u8), seeds: &[&[u8]]) {
+        let (address, bump) = address;
+        let bump = [bump];
+        let seeds = [seeds, &[&bump[..]]].concat();
+        assert_eq!(Pubkey::create_program_address(&seeds, &crate::ID), Ok(address));
+    }
+
+    #[test]
+    fn addresses_match_the_program_seeds() {
+        let config = Pubkey::new_unique();
+        let (mint_x, mint_y) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_derived(config_address(42), &[b"config", &42u64.to_le_bytes()]);
+        assert_derived(lp_mint_address(&config), &[b"lp", config.as_ref()]);
+        assert_derived(
+            pool_entry_address(&mint_x, &mint_y, 30),
+            &[b"pool", mint_x.as_ref(), mint_y.as_ref(), &30u16.to_le_bytes()]
+        );
+        assert_derived(pool_index_address(7), &[b"pool", &7u64.to_le_bytes()]);
+        // both live under b"pool", the seed lengths keep them apart
+        assert_ne!(pool_entry_address(&mint_x, &mint_y, 0).0, pool_index_address(0).0);
+    }
+
+    #[test]
+    fn pool_addresses_put_each_token_program_on_its_own_side() {
+        let (mint_x, mint_y) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (program_x, program_y, program_lp) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let addresses = PoolAddresses::new(42, &mint_x, &mint_y, &program_x, &program_y, &program_lp);
+        let config = config_address(42).0;
+        assert_eq!(addresses.config, config);
+        assert_eq!(addresses.vault_x, vault_address(&config, &mint_x, &program_x));
+        assert_eq!(addresses.vault_y, vault_address(&config, &mint_y, &program_y));
+        assert_eq!(addresses.locked_lp, locked_lp_address(&config, &program_lp));
+    }
+}
@@ -0,0 +1,164 @@
+use anchor_lang::prelude::*;
+
+use crate::cpi::accounts::{ Deposit, Swap, Withdraw };
+
+// the pool side of a CPI into the amm, the same for every user of the pool
+#[derive(Clone)]
+pub struct PoolAccounts<'info> {
+    pub amm_program: AccountInfo<'info>,
+    pub config: AccountInfo<'info>,
+    pub oracle: AccountInfo<'info>,
+    pub mint_x: AccountInfo<'info>,
+    pub mint_y: AccountInfo<'info>,
+    pub mint_lp: AccountInfo<'info>,
+    pub vault_x: AccountInfo<'info>,
+    pub vault_y: AccountInfo<'info>,
+    pub locked_lp: AccountInfo<'info>,
+    // only needed once the pool has a price band
+    pub reference_price: Option<AccountInfo<'info>>,
+    // the lp mint's program
+    pub token_program: AccountInfo<'info>,
+    pub token_program_x: AccountInfo<'info>,
+    pub token_program_y: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub associated_token_program: AccountInfo<'info>,
+}
+
+// the trading side; when `user` is a PDA of the calling program it signs through `signer_seeds`
+#[derive(Clone)]
+pub struct UserAccounts<'info> {
+    pub user: AccountInfo<'info>,
+    pub user_x: AccountInfo<'info>,
+    pub user_y: AccountInfo<'info>,
+    pub user_lp: AccountInfo<'info>,
+    // only needed once the pool has an allowlist
+    pub allowlist_entry: Option<AccountInfo<'info>>,
+}
+
+// a CPI runs inside the caller's transaction, so these never set an expiry of their own.
+// `referrer` is a token account of the input mint; its cut is paid from the input vault out of
+// the LP fee, so naming one doesn't change what the trade itself pays or receives
+impl<'info> PoolAccounts<'info> {
+    pub fn swap(
+        &self,
+        user: &UserAccounts<'info>,
+        signer_seeds: &[&[&[u8]]],
+        referrer: Option<AccountInfo<'info>>,
+        is_x: bool,
+        amount_in: u64,
+        min_out: u64
+    ) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.amm_program.clone(),
+            self.swap_accounts(user, referrer),
+            signer_seeds
+        );
+        crate::cpi::swap(ctx, is_x, amount_in, min_out, None)
+    }
+
+    pub fn swap_exact_out(
+        &self,
+        user: &UserAccounts<'info>,
+        signer_seeds: &[&[&[u8]]],
+        referrer: Option<AccountInfo<'info>>,
+        is_x: bool,
+        amount_out: u64,
+        max_in: u64
+    ) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.amm_program.clone(),
+            self.swap_accounts(user, referrer),
+            signer_seeds
+        );
+        crate::cpi::swap_exact_out(ctx, is_x, amount_out, max_in, None)
+    }
+
+    pub fn deposit(
+        &self,
+        user: &UserAccounts<'info>,
+        signer_seeds: &[&[&[u8]]],
+        amount: u64,
+        max_x: u64,
+        max_y: u64
+    ) -> Result<()> {
+        let accounts = Deposit {
+            user: user.user.clone(),
+            mint_x: self.mint_x.clone(),
+            mint_y: self.mint_y.clone(),
+            mint_lp: self.mint_lp.clone(),
+            user_x: user.user_x.clone(),
+            user_y: user.user_y.clone(),
+            user_lp: user.user_lp.clone(),
+            locked_lp: self.locked_lp.clone(),
+            vault_x: self.vault_x.clone(),
+            vault_y: self.vault_y.clone(),
+            config: self.config.clone(),
+            oracle: self.oracle.clone(),
+            allowlist_entry: user.allowlist_entry.clone(),
+            system_program: self.system_program.clone(),
+            associated_token_program: self.associated_token_program.clone(),
+            token_program: self.token_program.clone(),
+            token_program_x: self.token_program_x.clone(),
+            token_program_y: self.token_program_y.clone(),
+        };
+        let ctx = CpiContext::new_with_signer(self.amm_program.clone(), accounts, signer_seeds);
+        crate::cpi::deposit(ctx, amount, max_x, max_y, None)
+    }
+
+    pub fn withdraw(
+        &self,
+        user: &UserAccounts<'info>,
+        signer_seeds: &[&[&[u8]]],
+        amount: u64,
+        min_x: u64,
+        min_y: u64
+    ) -> Result<()> {
+        let accounts = Withdraw {
+            user: user.user.clone(),
+            mint_x: self.mint_x.clone(),
+            mint_y: self.mint_y.clone(),
+            config: self.config.clone(),
+            oracle: self.oracle.clone(),
+            mint_lp: self.mint_lp.clone(),
+            vault_x: self.vault_x.clone(),
+            vault_y: self.vault_y.clone(),
+            user_x: user.user_x.clone(),
+            user_y: user.user_y.clone(),
+            user_lp: user.user_lp.clone(),
+            allowlist_entry: user.allowlist_entry.clone(),
+            reference_price: self.reference_price.clone(),
+            token_program: self.token_program.clone(),
+            token_program_x: self.token_program_x.clone(),
+            token_program_y: self.token_program_y.clone(),
+            system_program: self.system_program.clone(),
+            associated_token_program: self.associated_token_program.clone(),
+        };
+        let ctx = CpiContext::new_with_signer(self.amm_program.clone(), accounts, signer_seeds);
+        crate::cpi::withdraw(ctx, amount, min_x, min_y, None)
+    }
+
+    fn swap_accounts(
+        &self,
+        user: &UserAccounts<'info>,
+        referrer: Option<AccountInfo<'info>>
+    ) -> Swap<'info> {
+        Swap {
+            user: user.user.clone(),
+            mint_x: self.mint_x.clone(),
+            mint_y: self.mint_y.clone(),
+            user_x: user.user_x.clone(),
+            user_y: user.user_y.clone(),
+            vault_x: self.vault_x.clone(),
+            vault_y: self.vault_y.clone(),
+            config: self.config.clone(),
+            oracle: self.oracle.clone(),
+            allowlist_entry: user.allowlist_entry.clone(),
+            referrer,
+            reference_price: self.reference_price.clone(),
+            system_program: self.system_program.clone(),
+            associated_token_program: self.associated_token_program.clone(),
+            token_program_x: self.token_program_x.clone(),
+            token_program_y: self.token_program_y.clone(),
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use anchor_lang::prelude::*;
+
+// LP minted to the pool's own locked account on the first deposit, never redeemable
+#[constant]
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+// seconds between stored oracle observations for new pools, 32 of them cover half an hour
+pub const DEFAULT_OBSERVATION_INTERVAL: u32 = 60;
+
+// fixed point scale of Farm::reward_per_share
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
@@ -0,0 +1,367 @@
+use anchor_lang::prelude::*;
+use constant_product_curve::{ ConstantProduct, LiquidityPair, SwapResult, XYAmounts };
+
+use crate::{ error::AmmError, utils::isqrt };
+
+mod stable_swap;
+
+pub use stable_swap::*;
+
+pub const MAX_AMP: u64 = 1_000_000;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum CurveType {
+    ConstantProduct,
+    // Curve-style amplification coefficient, higher is flatter around the 1:1 price
+    StableSwap {
+        amp: u64,
+    },
+}
+
+impl CurveType {
+    pub fn validate(&self) -> Result<()> {
+        if let CurveType::StableSwap { amp } = self {
+            require!(*amp > 0 && *amp <= MAX_AMP, AmmError::InvalidAmp);
+        }
+        Ok(())
+    }
+
+    // the fee is charged on the input, like ConstantProduct::swap does
+    pub fn swap(&self, x: u64, y: u64, fee: u16, is_x: bool, amount: u64) -> Result<SwapResult> {
+        match self {
+            CurveType::ConstantProduct => {
+                let mut curve = ConstantProduct::init(x, y, x, fee, None).map_err(
+                    |_| AmmError::CurveCalculation
+                )?;
+                let p = match is_x {
+                    true => LiquidityPair::X,
+                    false => LiquidityPair::Y,
+                };
+                curve.swap(p, amount, 0).map_err(|_| AmmError::CurveCalculation.into())
+            }
+            CurveType::StableSwap { amp } => {
+                let (reserve_in, reserve_out) = match is_x {
+                    true => (x, y),
+                    false => (y, x),
+                };
+                let curve = StableSwap::new(*amp);
+                let base = curve
+                    .swap_base(reserve_in, reserve_out)
+                    .ok_or(AmmError::CurveCalculation)?;
+                stable_swap(&curve, &base, fee, amount)
+            }
+        }
+    }
+
+    // input, fee included, that buys exactly `amount_out`. Rounds against the trader and
+    // re-runs the exact-in swap so the result never pays out more than the forward math
+    pub fn swap_exact_out(
+        &self,
+        x: u64,
+        y: u64,
+        fee: u16,
+        is_x: bool,
+        amount_out: u64
+    ) -> Result<SwapResult> {
+        let (reserve_in, reserve_out) = match is_x {
+            true => (x, y),
+            false => (y, x),
+        };
+        require!(amount_out > 0 && amount_out < reserve_out, AmmError::CurveCalculation);
+        let net = match self {
+            CurveType::ConstantProduct => {
+                let numerator = (reserve_in as u128) * (amount_out as u128);
+                let denominator = (reserve_out - amount_out) as u128;
+                u64::try_from(numerator.div_ceil(denominator)).ok()
+            }
+            CurveType::StableSwap { amp } =>
+                StableSwap::new(*amp).swap_in(reserve_in, reserve_out, amount_out),
+        }.ok_or(AmmError::CurveCalculation)?;
+        let gross = u64
+            ::try_from(((net as u128) * 10_000).div_ceil(10_000 - (fee as u128)))
+            .map_err(|_| AmmError::CurveCalculation)?;
+
+        let res = self.swap(x, y, fee, is_x, gross)?;
+        require!(res.withdraw >= amount_out, AmmError::CurveCalculation);
+        Ok(SwapResult { deposit: gross, withdraw: amount_out, fee: res.fee })
+    }
+
+    // D for StableSwap, sqrt(x·y) for constant product, i.e. liquidity in coin units
+    pub fn invariant(&self, x: u64, y: u64) -> Result<u128> {
+        match self {
+            CurveType::ConstantProduct => Ok(isqrt((x as u128) * (y as u128))),
+            CurveType::StableSwap { amp } =>
+                StableSwap::new(*amp).compute_d(x, y).ok_or(AmmError::CurveCalculation.into()),
+        }
+    }
+
+    // LP to burn for taking exactly `amount_x` and `amount_y` out. The part that deviates
+    // from a balanced withdrawal pays half the swap fee, as Curve does for two coins.
+    pub fn withdraw_imbalanced_lp(
+        &self,
+        x: u64,
+        y: u64,
+        fee: u16,
+        supply: u64,
+        amount_x: u64,
+        amount_y: u64
+    ) -> Result<u64> {
+        require!(amount_x > 0 || amount_y > 0, AmmError::ZeroAmount);
+        let new_x = x.checked_sub(amount_x).ok_or(AmmError::CurveCalculation)?;
+        let new_y = y.checked_sub(amount_y).ok_or(AmmError::CurveCalculation)?;
+        let d0 = self.invariant(x, y)?;
+        let d1 = self.invariant(new_x, new_y)?;
+        require!(d0 > 0, AmmError::EmptyPool);
+
+        let charge = |reserve: u64, new_reserve: u64| -> Result<u64> {
+            let ideal = mul_div(reserve as u128, d1, d0).ok_or(AmmError::CurveCalculation)?;
+            let difference = ideal.abs_diff(new_reserve as u128);
+            let penalty = difference * (fee as u128) / 20_000;
+            Ok(new_reserve.saturating_sub(penalty as u64))
+        };
+        let d2 = self.invariant(charge(x, new_x)?, charge(y, new_y)?)?;
+
+        // round in favour of the pool
+        let burned = mul_div_ceil(supply as u128, d0 - d2, d0).ok_or(AmmError::CurveCalculation)?;
+        u64::try_from(burned).map_err(|_| AmmError::CurveCalculation.into())
+    }
+
+    // balanced deposits and withdrawals keep the reserve ratio, which is
+    // invariant preserving for every curve, so both share the proportional math.
+    // Deposits round up and withdrawals down, so the rounding stays with the pool
+    pub fn deposit_amounts(&self, x: u64, y: u64, supply: u64, amount: u64) -> Result<XYAmounts> {
+        require!(supply > 0, AmmError::EmptyPool);
+        let share = |reserve: u64| {
+            u64::try_from(((reserve as u128) * (amount as u128)).div_ceil(supply as u128)).map_err(
+                |_| AmmError::CurveCalculation
+            )
+        };
+        Ok(XYAmounts { x: share(x)?, y: share(y)? })
+    }
+
+    pub fn withdraw_amounts(&self, x: u64, y: u64, supply: u64, amount: u64) -> Result<XYAmounts> {
+        require!(supply > 0, AmmError::EmptyPool);
+        require!(amount <= supply, AmmError::CurveCalculation);
+        let share = |reserve: u64| ((reserve as u128) * (amount as u128) / (supply as u128)) as u64;
+        Ok(XYAmounts { x: share(x), y: share(y) })
+    }
+
+    // single sided deposit of `amount` on one side: part of it is swapped through the curve
+    // so the rest matches the post swap ratio. Returns the LP to mint and the internal swap.
+    pub fn deposit_single(
+        &self,
+        x: u64,
+        y: u64,
+        fee: u16,
+        supply: u64,
+        is_x: bool,
+        amount: u64
+    ) -> Result<(u64, SwapResult)> {
+        let (reserve_in, reserve_out) = match is_x {
+            true => (x, y),
+            false => (y, x),
+        };
+        // the search prices up to 64 swaps against the same reserves, StableSwap's D only
+        // depends on those so it's found once rather than on every probe
+        let stable = match self {
+            CurveType::StableSwap { amp } => {
+                let curve = StableSwap::new(*amp);
+                let base = curve
+                    .swap_base(reserve_in, reserve_out)
+                    .ok_or(AmmError::CurveCalculation)?;
+                Some((curve, base))
+            }
+            CurveType::ConstantProduct => None,
+        };
+        // LP backed by each side after swapping `swapped`, the smaller one is what can be minted
+        let shares = |swapped: u64| -> Result<(u64, u64, SwapResult)> {
+            let res = match (swapped, &stable) {
+                (0, _) => SwapResult { deposit: 0, withdraw: 0, fee: 0 },
+                (_, Some((curve, base))) => stable_swap(curve, base, fee, swapped)?,
+                (_, None) => self.swap(x, y, fee, is_x, swapped)?,
+            };
+            let from_in = ((amount - swapped) as u128) * (supply as u128) /
+            ((reserve_in as u128) + (swapped as u128));
+            let from_out = (res.withdraw as u128) * (supply as u128) /
+            ((reserve_out - res.withdraw) as u128);
+            Ok((from_in as u64, from_out as u64, res))
+        };
+
+        // from_in falls and from_out rises with the swapped amount, search for the crossing
+        let (mut lo, mut hi) = (0u64, amount);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let (from_in, from_out) = match shares(mid) {
+                Ok((from_in, from_out, _)) => (from_in, from_out),
+                // too small to produce any output yet
+                Err(_) => (u64::MAX, 0),
+            };
+            // within a unit of each other is as close as the LP amounts get
+            if from_in.abs_diff(from_out) <= 1 {
+                lo = mid;
+                break;
+            }
+            match from_in > from_out {
+                true => {
+                    lo = mid + 1;
+                }
+                false => {
+                    hi = mid;
+                }
+            }
+        }
+        // the optimum sits on either side of the crossing
+        let (from_in, from_out, res) = shares(lo)?;
+        if lo > 0 {
+            if let Ok((prev_in, prev_out, prev)) = shares(lo - 1) {
+                if prev_in.min(prev_out) > from_in.min(from_out) {
+                    return Ok((prev_in.min(prev_out), prev));
+                }
+            }
+        }
+        Ok((from_in.min(from_out), res))
+    }
+
+    // LP burn of `amount` paid out in one coin, the other side's share is swapped into it
+    pub fn withdraw_single(
+        &self,
+        x: u64,
+        y: u64,
+        fee: u16,
+        supply: u64,
+        is_x: bool,
+        amount: u64
+    ) -> Result<(u64, SwapResult)> {
+        let amounts = self.withdraw_amounts(x, y, supply, amount)?;
+        let (x, y) = (x - amounts.x, y - amounts.y);
+        let (direct, swapped) = match is_x {
+            true => (amounts.x, amounts.y),
+            false => (amounts.y, amounts.x),
+        };
+        if swapped == 0 {
+            return Ok((direct, SwapResult { deposit: 0, withdraw: 0, fee: 0 }));
+        }
+        // swapping the other side into the requested one
+        let res = self.swap(x, y, fee, !is_x, swapped)?;
+        Ok((direct + res.withdraw, res))
+    }
+}
+
+// StableSwap swap of `amount` against `base`, the fee is charged on the input
+fn stable_swap(curve: &StableSwap, base: &SwapBase, fee: u16, amount: u64) -> Result<SwapResult> {
+    let fee_amount = (((amount as u128) * (fee as u128)) / 10_000) as u64;
+    let withdraw = curve
+        .swap_out_from(base, amount - fee_amount)
+        .ok_or(AmmError::CurveCalculation)?;
+    require!(withdraw > 0, AmmError::CurveCalculation);
+    Ok(SwapResult { deposit: amount, withdraw, fee: fee_amount })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const FEE: u16 = 30;
+
+    fn curves() -> impl Strategy<Value = CurveType> {
+        prop_oneof![
+            Just(CurveType::ConstantProduct),
+            Just(CurveType::StableSwap { amp: 1 }),
+            Just(CurveType::StableSwap { amp: 100 }),
+            Just(CurveType::StableSwap { amp: MAX_AMP })
+        ]
+    }
+
+    // y per x at the margin, -dy/dx along the invariant
+    fn marginal_price(curve: &CurveType, x: u64, y: u64) -> f64 {
+        let (x_f, y_f) = (x as f64, y as f64);
+        match curve {
+            CurveType::ConstantProduct => y_f / x_f,
+            CurveType::StableSwap { amp } => {
+                let d = curve.invariant(x, y).unwrap() as f64;
+                let ann = 2.0 * (*amp as f64);
+                let d3 = d * d * d;
+                (ann + d3 / (4.0 * x_f * x_f * y_f)) / (ann + d3 / (4.0 * x_f * y_f * y_f))
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn imbalanced_withdraw_costs_more_than_balanced(
+            curve in curves(),
+            x in 1_000_000u64..1_000_000_000_000_000,
+            y in 1_000_000u64..1_000_000_000_000_000,
+            share_x in 0u64..1_000,
+            share_y in 0u64..1_000,
+        ) {
+            let (amount_x, amount_y) = (x / 1_000 * share_x, y / 1_000 * share_y);
+            let supply = curve.invariant(x, y).unwrap() as u64;
+            let Ok(burned) = curve.withdraw_imbalanced_lp(x, y, FEE, supply, amount_x, amount_y) else {
+                return Ok(());
+            };
+            // a balanced withdrawal worth the same at the current price, in x
+            let price = marginal_price(&curve, x, y);
+            let value = (amount_x as f64) + (amount_y as f64) / price;
+            let balanced = (supply as f64) * value / ((x as f64) + (y as f64) / price);
+            prop_assert!((burned as f64) >= balanced * (1.0 - 1e-9), "burned {} for {}", burned, balanced);
+        }
+
+        #[test]
+        fn imbalanced_withdraw_never_dilutes_the_invariant(
+            curve in curves(),
+            x in 1_000_000u64..1_000_000_000_000_000,
+            y in 1_000_000u64..1_000_000_000_000_000,
+            share_x in 0u64..1_000,
+            share_y in 0u64..1_000,
+        ) {
+            let (amount_x, amount_y) = (x / 1_000 * share_x, y / 1_000 * share_y);
+            let supply = curve.invariant(x, y).unwrap() as u64;
+            let Ok(burned) = curve.withdraw_imbalanced_lp(x, y, FEE, supply, amount_x, amount_y) else {
+                return Ok(());
+            };
+            prop_assert!(burned <= supply);
+            let d0 = curve.invariant(x, y).unwrap();
+            let d1 = curve.invariant(x - amount_x, y - amount_y).unwrap();
+            // d1 / (supply - burned) >= d0 / supply
+            prop_assert!(d1 * (supply as u128) >= d0 * ((supply - burned) as u128));
+        }
+
+        #[test]
+        fn balanced_withdraw_through_the_imbalanced_path_pays_no_fee(
+            curve in curves(),
+            x in 1_000_000u64..1_000_000_000_000_000,
+            y in 1_000_000u64..1_000_000_000_000_000,
+            share in 1u64..1_000,
+        ) {
+            let supply = curve.invariant(x, y).unwrap() as u64;
+            let lp = supply / 1_000 * share;
+            let amounts = curve.withdraw_amounts(x, y, supply, lp).unwrap();
+            let burned = curve.withdraw_imbalanced_lp(x, y, FEE, supply, amounts.x, amounts.y).unwrap();
+            // only rounding apart, the amounts themselves were rounded down from `lp`
+            prop_assert!(burned <= lp + 2, "burned {} for {}", burned, lp);
+        }
+    }
+
+    #[test]
+    fn imbalanced_withdraw_of_nothing_is_rejected() {
+        for curve in [CurveType::ConstantProduct, CurveType::StableSwap { amp: 100 }] {
+            let supply = curve.invariant(1_000_000, 1_000_000).unwrap() as u64;
+            let err = curve.withdraw_imbalanced_lp(1_000_000, 1_000_000, FEE, supply, 0, 0);
+            assert_eq!(err.unwrap_err(), AmmError::ZeroAmount.into());
+        }
+    }
+
+    #[test]
+    fn imbalanced_withdraw_rounds_up_only_a_remainder() {
+        // a balanced third of the pool, so no fee and d0 - d2 is exactly d0 / 3
+        let curve = CurveType::ConstantProduct;
+        let burned = |supply| {
+            curve.withdraw_imbalanced_lp(3_000_000, 3_000_000, FEE, supply, 1_000_000, 1_000_000)
+        };
+        assert_eq!(burned(3_000_000).unwrap(), 1_000_000);
+        // a third of this supply leaves a fraction, which the pool keeps
+        assert_eq!(burned(1_000_000).unwrap(), 333_334);
+    }
+}
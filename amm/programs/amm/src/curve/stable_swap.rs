@@ -0,0 +1,324 @@
+// two coin StableSwap invariant: A·n^n·(x + y) + D = A·D·n^n + D^(n+1) / (n^n·x·y)
+// everything is done in u128 and returns None on overflow or non convergence
+
+const N_COINS: u128 = 2;
+const MAX_ITERATIONS: usize = 255;
+
+pub struct StableSwap {
+    amp: u128,
+}
+
+impl StableSwap {
+    pub fn new(amp: u64) -> Self {
+        StableSwap { amp: amp as u128 }
+    }
+
+    pub fn compute_d(&self, x: u64, y: u64) -> Option<u128> {
+        // fixed order, so the rounding and with it D doesn't depend on which coin is which
+        let (x, y) = (x.min(y) as u128, x.max(y) as u128);
+        let sum = x + y;
+        if sum == 0 {
+            return Some(0);
+        }
+        if x == 0 || y == 0 {
+            return None;
+        }
+        let ann = self.amp * N_COINS;
+        let mut d = sum;
+        for _ in 0..MAX_ITERATIONS {
+            let d_p = mul_div(mul_div(d, d, x * N_COINS)?, d, y * N_COINS)?;
+            let d_prev = d;
+            let numerator = ann.checked_mul(sum)?.checked_add(d_p.checked_mul(N_COINS)?)?;
+            let denominator = (ann - 1)
+                .checked_mul(d)?
+                .checked_add((N_COINS + 1).checked_mul(d_p)?)?;
+            d = mul_div(numerator, d, denominator)?;
+            // from d = x + y Newton only ever comes down to D, once it stops doing so it's
+            // circling D in rounding noise, which happens on very lopsided pools
+            if d + 1 >= d_prev {
+                return Some(d.min(d_prev));
+            }
+        }
+        None
+    }
+
+    // balance of the other coin that keeps the invariant at `d` when one coin holds `balance`
+    pub fn compute_y(&self, balance: u64, d: u128) -> Option<u128> {
+        let balance = balance as u128;
+        if balance == 0 {
+            return None;
+        }
+        let ann = self.amp * N_COINS;
+        let c = mul_div(mul_div(d, d, balance * N_COINS)?, d, ann * N_COINS)?;
+        let b = balance + d / ann;
+        let mut y = d;
+        for _ in 0..MAX_ITERATIONS {
+            let y_prev = y;
+            let numerator = y.checked_mul(y)?.checked_add(c)?;
+            let denominator = (N_COINS * y + b).checked_sub(d)?;
+            y = numerator / denominator;
+            if y.abs_diff(y_prev) <= 1 {
+                return Some(y);
+            }
+        }
+        None
+    }
+
+    // output for `amount` of the input coin after the fee has been taken out
+    pub fn swap_out(&self, reserve_in: u64, reserve_out: u64, amount: u64) -> Option<u64> {
+        self.swap_out_from(&self.swap_base(reserve_in, reserve_out)?, amount)
+    }
+
+    // input needed, after the fee has been taken out, to take `amount` of the output coin.
+    // Solves the curve for it, then steps up until swap_out really pays `amount`
+    pub fn swap_in(&self, reserve_in: u64, reserve_out: u64, amount: u64) -> Option<u64> {
+        let base = self.swap_base(reserve_in, reserve_out)?;
+        let target = base.start.checked_sub((amount as u128).checked_add(1)?)?;
+        let new_in = self.compute_y(u64::try_from(target).ok()?, base.d)?;
+        let mut needed = u64::try_from(new_in.saturating_sub(reserve_in as u128)).ok()?;
+        for _ in 0..MAX_ITERATIONS {
+            let out = self.swap_out_from(&base, needed).unwrap_or(0);
+            if out >= amount {
+                return Some(needed);
+            }
+            // scale the shortfall by the current rate, the input coin may be worth many outputs
+            let step = match out {
+                0 => needed.max(1),
+                _ => (mul_div((amount - out) as u128, needed as u128, out as u128)? as u64).max(1),
+            };
+            needed = needed.checked_add(step)?;
+        }
+        None
+    }
+
+    // D and y are only found to within 1, so swaps are measured from where the curve itself
+    // puts the output balance rather than from the reserve, or a zero swap could pay out
+    pub fn swap_base(&self, reserve_in: u64, reserve_out: u64) -> Option<SwapBase> {
+        let d = self.compute_d(reserve_in, reserve_out)?;
+        let start = self.compute_y(reserve_in, d)?.min(reserve_out as u128);
+        Some(SwapBase { reserve_in, d, start })
+    }
+
+    // same as swap_out, for pricing several swaps against the same reserves
+    pub fn swap_out_from(&self, base: &SwapBase, amount: u64) -> Option<u64> {
+        let new_out = self.compute_y(base.reserve_in.checked_add(amount)?, base.d)?;
+        // round in favour of the pool
+        let out = base.start.checked_sub(new_out)?.checked_sub(1)?;
+        u64::try_from(out).ok()
+    }
+}
+
+// what swaps out of a given pair of reserves share, D is the expensive part
+pub struct SwapBase {
+    reserve_in: u64,
+    d: u128,
+    start: u128,
+}
+
+// a * b / c with a 256 bit intermediate product
+pub fn mul_div(a: u128, b: u128, c: u128) -> Option<u128> {
+    if c == 0 {
+        return None;
+    }
+    if let Some(product) = a.checked_mul(b) {
+        return Some(product / c);
+    }
+    let (hi, lo) = widening_mul(a, b);
+    if hi >= c {
+        return None;
+    }
+    // restoring long division of (hi, lo) by c, quotient fits in u128 since hi < c
+    let (mut rem, mut quotient) = (hi, 0u128);
+    for i in (0..128).rev() {
+        let carry = rem >> 127;
+        rem = (rem << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || rem >= c {
+            rem = rem.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+    Some(quotient)
+}
+
+// a * b / c rounded up, for amounts the pool takes
+pub fn mul_div_ceil(a: u128, b: u128, c: u128) -> Option<u128> {
+    let quotient = mul_div(a, b, c)?;
+    match widening_mul(quotient, c) == widening_mul(a, b) {
+        true => Some(quotient),
+        false => quotient.checked_add(1),
+    }
+}
+
+fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_hi = a_hi * b_hi;
+    let cross = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let lo = (cross << 64) | (lo_lo & MASK);
+    let hi = hi_hi + (hi_lo >> 64) + (lo_hi >> 64) + (cross >> 64);
+    (hi, lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ curve::MAX_AMP, utils::isqrt };
+    use proptest::prelude::*;
+
+    const AMPS: [u64; 3] = [1, 100, MAX_AMP];
+
+    proptest! {
+        #[test]
+        fn d_is_stable_under_zero_swap(
+            x in 1_000u64..1_000_000_000_000_000,
+            y in 1_000u64..1_000_000_000_000_000,
+        ) {
+            for amp in AMPS {
+                let curve = StableSwap::new(amp);
+                let d = curve.compute_d(x, y).unwrap();
+                prop_assert_eq!(curve.compute_d(y, x), Some(d));
+                // D is only exact to a unit, which lopsided pools magnify a little in y
+                let y1 = curve.compute_y(x, d).unwrap();
+                let tolerance = 2 + (y as u128) / 1_000_000_000;
+                prop_assert!(y1.abs_diff(y as u128) <= tolerance, "amp {} moved y from {} to {}", amp, y, y1);
+                prop_assert_eq!(curve.swap_out(x, y, 0).unwrap_or(0), 0);
+            }
+        }
+
+        #[test]
+        fn d_lies_between_product_and_sum(
+            x in 1u64..1_000_000_000_000_000,
+            y in 1u64..1_000_000_000_000_000,
+        ) {
+            // the curve sits between x·y = (D/2)² and x + y = D, at the peg D is x + y
+            for amp in AMPS {
+                let d = StableSwap::new(amp).compute_d(x, y).unwrap();
+                let sum = (x as u128) + (y as u128);
+                prop_assert!(d <= sum);
+                prop_assert!(d + 2 >= 2 * isqrt((x as u128) * (y as u128)));
+                prop_assert_eq!(StableSwap::new(amp).compute_d(x, x), Some(2 * (x as u128)));
+            }
+        }
+
+        #[test]
+        fn swaps_are_symmetric_around_the_peg(
+            reserve in 1_000_000u64..1_000_000_000_000_000,
+            share in 1u64..1_000,
+        ) {
+            let amount = reserve / 1_000 * share;
+            for amp in AMPS {
+                let curve = StableSwap::new(amp);
+                let out = curve.swap_out(reserve, reserve, amount).unwrap();
+                // selling the output straight back can't return more than went in, and
+                // only loses the rounding each leg keeps for the pool
+                let back = curve.swap_out(reserve - out, reserve + amount, out).unwrap();
+                prop_assert!(back <= amount);
+                prop_assert!(amount - back <= 4, "amp {} lost {} on a round trip", amp, amount - back);
+            }
+        }
+
+        #[test]
+        fn output_never_exceeds_constant_sum(
+            reserve_out in 1_000u64..1_000_000_000_000_000,
+            excess in 0u64..1_000_000_000_000_000,
+            amount in 1u64..1_000_000_000_000_000,
+        ) {
+            // from the peg or the side holding more of the input coin, no curve between
+            // constant product and constant sum pays more than 1:1
+            let reserve_in = reserve_out.saturating_add(excess);
+            for amp in AMPS {
+                if let Some(out) = StableSwap::new(amp).swap_out(reserve_in, reserve_out, amount) {
+                    prop_assert!(out <= amount && out < reserve_out);
+                }
+            }
+        }
+
+        #[test]
+        fn swap_in_covers_swap_out(
+            reserve_in in 1_000_000u64..1_000_000_000_000_000,
+            reserve_out in 1_000_000u64..1_000_000_000_000_000,
+            share in 1u64..900,
+        ) {
+            let amount = reserve_out / 1_000 * share;
+            for amp in AMPS {
+                let curve = StableSwap::new(amp);
+                let needed = curve.swap_in(reserve_in, reserve_out, amount).unwrap();
+                prop_assert!(curve.swap_out(reserve_in, reserve_out, needed).unwrap() >= amount);
+            }
+        }
+
+        #[test]
+        fn mul_div_matches_u128_on_small_inputs(
+            a in 0u128..(u64::MAX as u128),
+            b in 0u128..(u64::MAX as u128),
+            c in 1u128..u128::MAX,
+        ) {
+            prop_assert_eq!(mul_div(a, b, c), Some((a * b) / c));
+        }
+
+        #[test]
+        fn mul_div_is_exact_past_u128(a in any::<u128>(), b in 1u128..) {
+            prop_assert_eq!(mul_div(a, b, b), Some(a));
+            prop_assert_eq!(mul_div(b, a, b), Some(a));
+            prop_assert_eq!(mul_div_ceil(a, b, b), Some(a));
+        }
+
+        #[test]
+        fn mul_div_ceil_rounds_up_only_a_remainder(
+            a in any::<u64>(),
+            b in any::<u64>(),
+            c in 1u64..,
+        ) {
+            let (a, b, c) = (a as u128, b as u128, c as u128);
+            prop_assert_eq!(mul_div_ceil(a, b, c), Some((a * b).div_ceil(c)));
+        }
+    }
+
+    #[test]
+    fn mul_div_overflowing_products() {
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(mul_div(u128::MAX, 2, 4), Some(u128::MAX / 2));
+        assert_eq!(mul_div(1 << 100, 1 << 100, 1 << 120), Some(1 << 80));
+        assert_eq!(mul_div((1 << 127) + 1, 4, 2), None);
+        assert_eq!(mul_div(u128::MAX, 2, 1), None);
+        assert_eq!(mul_div(1, 1, 0), None);
+        assert_eq!(mul_div_ceil(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(mul_div_ceil(u128::MAX, 3, 4), Some(u128::MAX - u128::MAX / 4));
+        assert_eq!(mul_div_ceil(u128::MAX, u128::MAX - 1, u128::MAX), Some(u128::MAX - 1));
+        assert_eq!(mul_div_ceil(u128::MAX, 2, 2), Some(u128::MAX));
+        assert_eq!(mul_div_ceil(1, 1, 0), None);
+    }
+
+    #[test]
+    fn degenerate_pools_fail_instead_of_panicking() {
+        for amp in AMPS {
+            let curve = StableSwap::new(amp);
+            assert_eq!(curve.compute_d(0, 0), Some(0));
+            assert_eq!(curve.compute_d(0, 1_000), None);
+            assert_eq!(curve.compute_y(0, 1_000), None);
+            assert_eq!(curve.swap_in(1_000, 1_000, 1_000), None);
+            assert_eq!(curve.swap_out(1_000, 1_000, u64::MAX), None);
+        }
+    }
+
+    #[test]
+    fn extreme_amps_converge_on_extreme_reserves() {
+        for amp in AMPS {
+            let curve = StableSwap::new(amp);
+            for (x, y) in [
+                (1, u64::MAX / 4),
+                (u64::MAX / 4, u64::MAX / 4),
+                (1_000, 1_000_000_000_000_000_000),
+                // Newton used to circle D here without ever settling within 1
+                (770_692_799_188_746, 31_348_554_387),
+            ] {
+                assert!(curve.compute_d(x, y).is_some(), "amp {} failed on ({}, {})", amp, x, y);
+            }
+        }
+    }
+}
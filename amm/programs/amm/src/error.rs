@@ -0,0 +1,69 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum AmmError {
+    #[msg("Pool is locked")]
+    PoolLocked,
+    #[msg("Signer is not the pool authority")]
+    Unauthorized,
+    #[msg("Fee must be less than 10000 basis points")]
+    InvalidFee,
+    #[msg("Protocol fee must not exceed 10000 basis points")]
+    InvalidProtocolFee,
+    #[msg("Referral and protocol fee shares must not exceed 10000 basis points together")]
+    InvalidReferralFee,
+    #[msg("Slippage limit exceeded")]
+    SlippageExceeded,
+    #[msg("Route accounts do not form a valid path")]
+    InvalidRoute,
+    #[msg("Requested observation is older than the oracle history")]
+    ObservationTooOld,
+    #[msg("Amplification coefficient out of range")]
+    InvalidAmp,
+    #[msg("Curve calculation failed")]
+    CurveCalculation,
+    #[msg("Token account does not match the requested side")]
+    InvalidTokenAccount,
+    #[msg("Pool has no liquidity")]
+    EmptyPool,
+    #[msg("A flash loan is outstanding")]
+    FlashLoanActive,
+    #[msg("No flash loan to repay")]
+    NoFlashLoan,
+    #[msg("Flash loan is not repaid later in the transaction")]
+    MissingFlashRepay,
+    #[msg("mint_x must sort before mint_y")]
+    MintsNotOrdered,
+    #[msg("Initial deposit does not cover the minimum liquidity")]
+    InsufficientInitialLiquidity,
+    #[msg("Not enough LP staked")]
+    InsufficientStake,
+    #[msg("Pool still holds liquidity")]
+    PoolNotEmpty,
+    #[msg("User is not on the pool's allowlist")]
+    NotAllowlisted,
+    #[msg("Transaction expired")]
+    Expired,
+    #[msg("Missing or invalid reference price account")]
+    InvalidReferencePrice,
+    #[msg("Trade would move the pool price outside the price band")]
+    PriceOutOfBand,
+    #[msg("Decay period must be greater than zero")]
+    InvalidDecayPeriod,
+    #[msg("Observation interval must be greater than zero")]
+    InvalidObservationInterval,
+    #[msg("Pool still has allowlist entries")]
+    AllowlistNotEmpty,
+    #[msg("Route swaps through the same pool twice in a row")]
+    RepeatedPool,
+    #[msg("Pool has a farm, its accounts are needed to close the pool")]
+    MissingFarmAccounts,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Vault holds less than the accrued protocol fees")]
+    InsufficientReserves,
+    #[msg("Referrer can't be the trader")]
+    SelfReferral,
+    #[msg("Amount must be greater than zero")]
+    ZeroAmount,
+}
@@ -0,0 +1,174 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{ AllowlistMode, DynamicFee, PriceBand };
+
+// amounts are what actually moved in or out of the vaults, after any transfer fees, and
+// reserves are the LP reserves (net of protocol fees) once the instruction is done
+
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepositEvent {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub lp_amount: u64,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithdrawEvent {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub lp_amount: u64,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+}
+
+// one per pool, so a routed swap emits one per hop
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapEvent {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub is_x: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    // charged on the input, protocol share included
+    pub fee: u64,
+    // part of `fee` paid out to the referrer
+    pub referral_fee: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlashLoanEvent {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub is_x: bool,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectProtocolFeesEvent {
+    pub config: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+}
+
+// whatever backed the locked liquidity, swept to the authority's treasury accounts
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosePoolEvent {
+    pub config: Pubkey,
+    pub authority: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateFarmEvent {
+    pub config: Pubkey,
+    pub farm: Pubkey,
+    pub reward_mint: Pubkey,
+    pub reward_rate: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FundFarmEvent {
+    pub config: Pubkey,
+    pub farm: Pubkey,
+    pub funder: Pubkey,
+    // as received by the reward vault
+    pub amount: u64,
+    pub undistributed: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StakeEvent {
+    pub config: Pubkey,
+    pub farm: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub total_staked: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnstakeEvent {
+    pub config: Pubkey,
+    pub farm: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub total_staked: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClaimEvent {
+    pub config: Pubkey,
+    pub farm: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+// the pool's settings after any update_config instruction, so an indexer can just overwrite
+// what it had rather than replay each setter
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateConfigEvent {
+    pub config: Pubkey,
+    // None once renounced
+    pub authority: Option<Pubkey>,
+    pub fee: u16,
+    pub dynamic_fee: Option<DynamicFee>,
+    pub protocol_fee: u16,
+    pub referral_fee: u16,
+    pub allowlist: Option<AllowlistMode>,
+    pub price_band: Option<PriceBand>,
+    pub locked: bool,
+}
+
+// also emitted when an existing entry's permissions change
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddToAllowlistEvent {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub can_deposit: bool,
+    pub can_swap: bool,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoveFromAllowlistEvent {
+    pub config: Pubkey,
+    pub user: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateLpMetadataEvent {
+    pub config: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetObservationIntervalEvent {
+    pub config: Pubkey,
+    pub interval: u32,
+}
@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::{ error::AmmError, events::AddToAllowlistEvent, state::{ AllowlistEntry, Config } };
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct AddToAllowlist<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"allowlist", config.key().as_ref(), user.as_ref()],
+        bump,
+        space = 8 + AllowlistEntry::INIT_SPACE
+    )]
+    pub allowlist_entry: Account<'info, AllowlistEntry>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AddToAllowlist<'info> {
+    // also updates the permissions of an existing entry
+    pub fn add_to_allowlist(
+        &mut self,
+        user: Pubkey,
+        can_deposit: bool,
+        can_swap: bool,
+        bumps: AddToAllowlistBumps
+    ) -> Result<()> {
+        // a fresh entry is still zeroed
+        if self.allowlist_entry.config == Pubkey::default() {
+            self.config.allowlist_entries = self.config.allowlist_entries
+                .checked_add(1)
+                .ok_or(AmmError::MathOverflow)?;
+        }
+        self.allowlist_entry.set_inner(AllowlistEntry {
+            config: self.config.key(),
+            user,
+            can_deposit,
+            can_swap,
+            bump: bumps.allowlist_entry,
+        });
+        emit!(AddToAllowlistEvent { config: self.config.key(), user, can_deposit, can_swap });
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{ transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked },
+};
+
+use crate::{ events::ClaimEvent, state::{ Farm, UserStake } };
+
+#[derive(Accounts)]
+pub struct Claim<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"farm", farm.config.as_ref()],
+        bump = farm.bump,
+        has_one = reward_mint
+    )]
+    pub farm: Account<'info, Farm>,
+    #[account(
+        mut,
+        seeds = [b"stake", farm.key().as_ref(), user.key().as_ref()],
+        bump = user_stake.bump
+    )]
+    pub user_stake: Account<'info, UserStake>,
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = reward_mint,
+        associated_token::authority = user,
+        associated_token::token_program = reward_token_program
+    )]
+    pub user_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = reward_token_program
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> Claim<'info> {
+    pub fn claim(&mut self) -> Result<()> {
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+        self.user_stake.settle(&self.farm)?;
+        let amount = self.user_stake.pending;
+        if amount == 0 {
+            return Ok(());
+        }
+        self.user_stake.pending = 0;
+        emit!(ClaimEvent {
+            config: self.farm.config,
+            farm: self.farm.key(),
+            user: self.user.key(),
+            amount,
+        });
+
+        let cpi_accounts = TransferChecked {
+            from: self.reward_vault.to_account_info(),
+            to: self.user_reward.to_account_info(),
+            mint: self.reward_mint.to_account_info(),
+            authority: self.farm.to_account_info(),
+        };
+        let seeds = &[&b"farm"[..], self.farm.config.as_ref(), &[self.farm.bump]];
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.reward_token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+        transfer_checked(cpi_ctx, amount, self.reward_mint.decimals)
+    }
+}
@@ -0,0 +1,317 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        burn,
+        close_account,
+        transfer_checked,
+        Burn,
+        CloseAccount,
+        Mint,
+        TokenAccount,
+        TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{
+    constants::MINIMUM_LIQUIDITY,
+    error::AmmError,
+    events::ClosePoolEvent,
+    state::{ Config, Farm, Oracle, PoolEntry, PoolIndex },
+};
+
+#[derive(Accounts)]
+pub struct ClosePool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        has_one = mint_x,
+        has_one = mint_y,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"pool", mint_x.key().as_ref(), mint_y.key().as_ref(), pool_entry.fee.to_le_bytes().as_ref()],
+        bump = pool_entry.bump,
+        has_one = config
+    )]
+    pub pool_entry: Box<Account<'info, PoolEntry>>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"pool", pool_entry.index.to_le_bytes().as_ref()],
+        bump = pool_index.bump,
+        has_one = config
+    )]
+    pub pool_index: Box<Account<'info, PoolIndex>>,
+    #[account(
+        mut,
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = token_program
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = config,
+        associated_token::token_program = token_program
+    )]
+    pub locked_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    // receive whatever dust and protocol fees are left in the vaults
+    #[account(
+        mut,
+        token::mint = mint_x,
+        token::token_program = token_program_x
+    )]
+    pub treasury_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mint_y,
+        token::token_program = token_program_y
+    )]
+    pub treasury_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    // the farm and its vaults, needed once create_farm has run
+    #[account(
+        mut,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump,
+        has_one = config
+    )]
+    pub farm: Option<Box<Account<'info, Farm>>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program
+    )]
+    pub farm_lp_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = reward_token_program
+    )]
+    pub reward_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    // receives the rewards still in the farm, claimed or not
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::token_program = reward_token_program
+    )]
+    pub treasury_reward: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    pub reward_token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> ClosePool<'info> {
+    // Config, the oracle, the registry entries, the farm and all token accounts go back to the
+    // authority, allowlist entries have to be removed first so their rent isn't stranded. Staked
+    // LP counts towards the supply, so stakers have to unstake before the pool can close, but
+    // rewards they haven't claimed by then are swept with the rest of the farm. SPL Token mints
+    // can't be closed, so mint_lp stays behind with zero supply and, as initialize creates it,
+    // the config seed can't be used again; the pair and fee tier can, under a new seed.
+    pub fn close_pool(&mut self) -> Result<()> {
+        require!(self.config.flash_loan.is_none(), AmmError::FlashLoanActive);
+        require!(self.config.allowlist_entries == 0, AmmError::AllowlistNotEmpty);
+        // nothing but the liquidity locked by the first deposit may be outstanding
+        require!(
+            self.mint_lp.supply == 0 ||
+                (self.mint_lp.supply == MINIMUM_LIQUIDITY &&
+                    self.locked_lp.amount == MINIMUM_LIQUIDITY),
+            AmmError::PoolNotEmpty
+        );
+
+        let (amount_x, amount_y) = (self.vault_x.amount, self.vault_y.amount);
+        if self.locked_lp.amount > 0 {
+            self.burn_locked_lp()?;
+        }
+        self.close(
+            self.locked_lp.to_account_info(),
+            self.token_program.to_account_info()
+        )?;
+        if self.config.has_farm {
+            self.close_farm()?;
+        }
+
+        for is_x in [true, false] {
+            let (amount, vault, program) = match is_x {
+                true => (
+                    amount_x,
+                    self.vault_x.to_account_info(),
+                    self.token_program_x.to_account_info(),
+                ),
+                false => (
+                    amount_y,
+                    self.vault_y.to_account_info(),
+                    self.token_program_y.to_account_info(),
+                ),
+            };
+            if amount > 0 {
+                self.sweep(is_x, amount)?;
+            }
+            self.close(vault, program)?;
+        }
+        emit!(ClosePoolEvent {
+            config: self.config.key(),
+            authority: self.authority.key(),
+            amount_x,
+            amount_y,
+        });
+        Ok(())
+    }
+
+    fn close_farm(&self) -> Result<()> {
+        let (
+            Some(farm),
+            Some(lp_vault),
+            Some(reward_mint),
+            Some(reward_vault),
+            Some(treasury),
+            Some(reward_program),
+        ) = (
+            &self.farm,
+            &self.farm_lp_vault,
+            &self.reward_mint,
+            &self.reward_vault,
+            &self.treasury_reward,
+            &self.reward_token_program,
+        ) else {
+            return err!(AmmError::MissingFarmAccounts);
+        };
+        require_keys_eq!(farm.reward_mint, reward_mint.key(), AmmError::MissingFarmAccounts);
+
+        let seeds = &[&b"farm"[..], farm.config.as_ref(), &[farm.bump]];
+        let signer_seeds = &[&seeds[..]];
+        if reward_vault.amount > 0 {
+            let cpi_accounts = TransferChecked {
+                from: reward_vault.to_account_info(),
+                to: treasury.to_account_info(),
+                mint: reward_mint.to_account_info(),
+                authority: farm.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                reward_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds
+            );
+            transfer_checked(cpi_ctx, reward_vault.amount, reward_mint.decimals)?;
+        }
+        for (account, cpi_program) in [
+            (lp_vault.to_account_info(), self.token_program.to_account_info()),
+            (reward_vault.to_account_info(), reward_program.to_account_info()),
+        ] {
+            let cpi_accounts = CloseAccount {
+                account,
+                destination: self.authority.to_account_info(),
+                authority: farm.to_account_info(),
+            };
+            close_account(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds))?;
+        }
+        farm.close(self.authority.to_account_info())
+    }
+
+    fn burn_locked_lp(&self) -> Result<()> {
+        let cpi_accounts = Burn {
+            mint: self.mint_lp.to_account_info(),
+            from: self.locked_lp.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+        let seeds = &[&b"config"[..], &self.config.seed.to_le_bytes(), &[self.config.config_bump]];
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+        burn(cpi_ctx, self.locked_lp.amount)
+    }
+
+    fn sweep(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true =>
+                (
+                    self.vault_x.to_account_info(),
+                    self.treasury_x.to_account_info(),
+                    self.mint_x.to_account_info(),
+                    self.mint_x.decimals,
+                    self.token_program_x.to_account_info(),
+                ),
+            false =>
+                (
+                    self.vault_y.to_account_info(),
+                    self.treasury_y.to_account_info(),
+                    self.mint_y.to_account_info(),
+                    self.mint_y.decimals,
+                    self.token_program_y.to_account_info(),
+                ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            to,
+            mint,
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[&b"config"[..], &self.config.seed.to_le_bytes(), &[self.config.config_bump]];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        transfer_checked(cpi_ctx, amount, decimals)
+    }
+
+    // closes a token account owned by config, rent goes to the authority
+    fn close(&self, account: AccountInfo<'info>, cpi_program: AccountInfo<'info>) -> Result<()> {
+        let cpi_accounts = CloseAccount {
+            account,
+            destination: self.authority.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[&b"config"[..], &self.config.seed.to_le_bytes(), &[self.config.config_bump]];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        close_account(cpi_ctx)
+    }
+}
@@ -0,0 +1,107 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{ transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked },
+};
+
+use crate::{ error::AmmError, events::CollectProtocolFeesEvent, state::Config };
+
+#[derive(Accounts)]
+pub struct CollectProtocolFees<'info> {
+    pub authority: Signer<'info>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        has_one = mint_x,
+        has_one = mint_y,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mint_x,
+        token::token_program = token_program_x
+    )]
+    pub treasury_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mint_y,
+        token::token_program = token_program_y
+    )]
+    pub treasury_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> CollectProtocolFees<'info> {
+    pub fn collect_protocol_fees(&mut self) -> Result<()> {
+        require!(self.config.flash_loan.is_none(), AmmError::FlashLoanActive);
+        let (amount_x, amount_y) = (self.config.protocol_fees_x, self.config.protocol_fees_y);
+        self.config.protocol_fees_x = 0;
+        self.config.protocol_fees_y = 0;
+
+        if amount_x > 0 {
+            self.withdraw_tokens(true, amount_x)?;
+        }
+        if amount_y > 0 {
+            self.withdraw_tokens(false, amount_y)?;
+        }
+        emit!(CollectProtocolFeesEvent { config: self.config.key(), amount_x, amount_y });
+        Ok(())
+    }
+
+    fn withdraw_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true =>
+                (
+                    self.vault_x.to_account_info(),
+                    self.treasury_x.to_account_info(),
+                    self.mint_x.to_account_info(),
+                    self.mint_x.decimals,
+                    self.token_program_x.to_account_info(),
+                ),
+            false =>
+                (
+                    self.vault_y.to_account_info(),
+                    self.treasury_y.to_account_info(),
+                    self.mint_y.to_account_info(),
+                    self.mint_y.decimals,
+                    self.token_program_y.to_account_info(),
+                ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            to,
+            mint,
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[&b"config"[..], &self.config.seed.to_le_bytes(), &[self.config.config_bump]];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        transfer_checked(cpi_ctx, amount, decimals)
+    }
+}
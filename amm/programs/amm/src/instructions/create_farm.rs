@@ -0,0 +1,79 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{ Mint, TokenAccount, TokenInterface },
+};
+
+use crate::{ error::AmmError, events::CreateFarmEvent, state::{ Config, Farm } };
+
+#[derive(Accounts)]
+pub struct CreateFarm<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = token_program
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"farm", config.key().as_ref()],
+        bump,
+        space = 8 + Farm::INIT_SPACE
+    )]
+    pub farm: Account<'info, Farm>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program
+    )]
+    pub lp_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = reward_token_program
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> CreateFarm<'info> {
+    pub fn create_farm(&mut self, reward_rate: u64, bumps: CreateFarmBumps) -> Result<()> {
+        self.config.has_farm = true;
+        self.farm.set_inner(Farm {
+            config: self.config.key(),
+            reward_mint: self.reward_mint.key(),
+            reward_rate,
+            reward_per_share: 0,
+            last_update: Clock::get()?.unix_timestamp,
+            total_staked: 0,
+            undistributed: 0,
+            bump: bumps.farm,
+        });
+        emit!(CreateFarmEvent {
+            config: self.config.key(),
+            farm: self.farm.key(),
+            reward_mint: self.reward_mint.key(),
+            reward_rate,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+use crate::state::Registry;
+
+#[derive(Accounts)]
+pub struct CreateRegistry<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"registry"],
+        bump,
+        space = 8 + Registry::INIT_SPACE
+    )]
+    pub registry: Account<'info, Registry>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateRegistry<'info> {
+    pub fn create_registry(&mut self, bumps: CreateRegistryBumps) -> Result<()> {
+        self.registry.set_inner(Registry {
+            pool_count: 0,
+            bump: bumps.registry,
+        });
+        Ok(())
+    }
+}
@@ -1,112 +1,213 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{ self, Mint, MintTo, Token, TokenAccount, Transfer },
+    token_interface::{
+        mint_to,
+        transfer_checked,
+        Mint,
+        MintTo,
+        TokenAccount,
+        TokenInterface,
+        TransferChecked,
+    },
 };
-use constant_product_curve::ConstantProduct;
 
-use crate::state::Config;
+use crate::{
+    constants::MINIMUM_LIQUIDITY,
+    error::AmmError,
+    events::DepositEvent,
+    state::{ AllowlistEntry, Config, Oracle },
+    utils::{ amount_received, amount_to_send, isqrt },
+};
 
 #[derive(Accounts)]
 pub struct Deposit<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         seeds = [b"lp".as_ref(), config.key().as_ref()],
         bump=config.lp_bump,
         mint::decimals = 6,
-        mint::authority = config
+        mint::authority = config,
+        mint::token_program = token_program
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_x,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program_x
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_y,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program_y
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_lp,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = config,
+        associated_token::token_program = token_program
     )]
-    pub user_lp: Account<'info, TokenAccount>,
+    pub locked_lp: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_x,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_y,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
+        has_one = mint_x,
+        has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
         bump=config.config_bump,
     )]
     pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+    // only needed on permissioned pools
+    #[account(
+        seeds = [b"allowlist", config.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_entry.bump
+    )]
+    pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
 }
 
 impl<'info> Deposit<'info> {
+    // on an empty pool `amount` is the minimum LP the caller accepts, otherwise the LP to mint
     pub fn deposit(&mut self, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
-        let (x, y) = match
-            self.mint_lp.supply == 0 &&
-            self.vault_x.amount == 0 &&
-            self.vault_y.amount == 0
-        {
-            true => (max_x, max_y),
-            false => {
-                let amounts = ConstantProduct::xy_deposit_amounts_from_l(
-                    self.vault_x.amount,
-                    self.vault_y.amount,
-                    self.mint_lp.supply,
-                    amount,
-                    6
-                ).unwrap();
-                (amounts.x, amounts.y)
-            }
-        };
+        self.config.check_tradable()?;
+        self.config.check_depositor(self.allowlist_entry.as_deref())?;
+        let (reserve_x, reserve_y) = self.config.reserves(
+            self.vault_x.amount,
+            self.vault_y.amount
+        )?;
+        self.oracle.update(Clock::get()?.unix_timestamp, reserve_x, reserve_y);
+        if self.mint_lp.supply == 0 {
+            return self.deposit_initial(amount, max_x, max_y);
+        }
+        let amounts = self.config.deposit_amounts(
+            reserve_x,
+            reserve_y,
+            self.mint_lp.supply,
+            amount
+        )?;
+        // the vaults must end up with the proportional amounts, so gross up for transfer fees
+        let (x, y) = (
+            amount_to_send(&self.mint_x, amounts.x)?,
+            amount_to_send(&self.mint_y, amounts.y)?,
+        );
+        require!(x <= max_x && y <= max_y, AmmError::SlippageExceeded);
         self.deposit_token(true, x)?;
         self.deposit_token(false, y)?;
-        self.deposit_lp(amount)
+        self.deposit_lp(self.user_lp.to_account_info(), amount)?;
+        self.emit_deposit(amount)
+    }
+
+    // LP starts at the geometric mean of what arrives, as in Uniswap v2, with
+    // MINIMUM_LIQUIDITY of it locked in the pool so the share price can't be inflated
+    fn deposit_initial(&mut self, min_lp: u64, x: u64, y: u64) -> Result<()> {
+        let liquidity = isqrt(
+            (amount_received(&self.mint_x, x)? as u128) * (amount_received(&self.mint_y, y)? as u128)
+        ) as u64;
+        require!(liquidity > MINIMUM_LIQUIDITY, AmmError::InsufficientInitialLiquidity);
+        let lp = liquidity - MINIMUM_LIQUIDITY;
+        require!(lp >= min_lp, AmmError::SlippageExceeded);
+
+        self.deposit_token(true, x)?;
+        self.deposit_token(false, y)?;
+        self.deposit_lp(self.locked_lp.to_account_info(), MINIMUM_LIQUIDITY)?;
+        self.deposit_lp(self.user_lp.to_account_info(), lp)?;
+        self.emit_deposit(lp)
+    }
+
+    // reports what reached the vaults by diffing them against the pre-instruction balances
+    fn emit_deposit(&mut self, lp_amount: u64) -> Result<()> {
+        let (before_x, before_y) = (self.vault_x.amount, self.vault_y.amount);
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        emit!(DepositEvent {
+            config: self.config.key(),
+            user: self.user.key(),
+            lp_amount,
+            amount_x: self.vault_x.amount - before_x,
+            amount_y: self.vault_y.amount - before_y,
+            reserve_x,
+            reserve_y,
+        });
+        Ok(())
     }
 
     fn deposit_token(&self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
-            true => (self.user_x.to_account_info(), self.vault_x.to_account_info()),
-            false => (self.user_y.to_account_info(), self.vault_y.to_account_info()),
+        let (from, to, mint, decimals, token_program) = match is_x {
+            true =>
+                (
+                    self.user_x.to_account_info(),
+                    self.vault_x.to_account_info(),
+                    self.mint_x.to_account_info(),
+                    self.mint_x.decimals,
+                    self.token_program_x.to_account_info(),
+                ),
+            false =>
+                (
+                    self.user_y.to_account_info(),
+                    self.vault_y.to_account_info(),
+                    self.mint_y.to_account_info(),
+                    self.mint_y.decimals,
+                    self.token_program_y.to_account_info(),
+                ),
         };
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from,
             to,
+            mint,
             authority: self.user.to_account_info(),
         };
-        let ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
-        token::transfer(ctx, amount)
+        let ctx = CpiContext::new(token_program, cpi_accounts);
+        transfer_checked(ctx, amount, decimals)
     }
 
-    fn deposit_lp(&self, amount: u64) -> Result<()> {
+    fn deposit_lp(&self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
         let cpi_accounts = MintTo {
             mint: self.mint_lp.to_account_info(),
-            to: self.user_lp.to_account_info(),
+            to,
             authority: self.config.to_account_info(),
         };
         let cpi_program = self.token_program.to_account_info();
@@ -115,6 +216,6 @@ impl<'info> Deposit<'info> {
             &[b"config", binding.as_ref(), &[self.config.config_bump]],
         ];
         let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-        token::mint_to(ctx, amount)
+        mint_to(ctx, amount)
     }
 }
@@ -0,0 +1,187 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        mint_to,
+        transfer_checked,
+        Mint,
+        MintTo,
+        TokenAccount,
+        TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{
+    error::AmmError,
+    events::DepositEvent,
+    state::{ AllowlistEntry, Config, Oracle },
+    utils::amount_received,
+};
+
+#[derive(Accounts)]
+pub struct DepositSingle<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"lp".as_ref(), config.key().as_ref()],
+        bump=config.lp_bump,
+        mint::decimals = 6,
+        mint::authority = config,
+        mint::token_program = token_program
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    // the user's account for whichever side is deposited
+    #[account(
+        mut,
+        token::authority = user,
+    )]
+    pub user_token: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_lp,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump=config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+    // only needed on permissioned pools, the zap needs both deposit and swap permission
+    #[account(
+        seeds = [b"allowlist", config.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_entry.bump
+    )]
+    pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+    /// CHECK: only read, checked against config.price_band
+    pub reference_price: Option<UncheckedAccount<'info>>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> DepositSingle<'info> {
+    pub fn deposit_single(&mut self, is_x: bool, amount: u64, min_lp: u64) -> Result<()> {
+        self.config.check_tradable()?;
+        self.config.check_depositor(self.allowlist_entry.as_deref())?;
+        self.config.check_trader(self.allowlist_entry.as_deref())?;
+        let mint_in = match is_x {
+            true => &self.mint_x,
+            false => &self.mint_y,
+        };
+        require_keys_eq!(self.user_token.mint, mint_in.key(), AmmError::InvalidTokenAccount);
+        // there is no price to zap against in an empty pool
+        require!(self.mint_lp.supply > 0, AmmError::EmptyPool);
+
+        let (x, y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        let now = Clock::get()?.unix_timestamp;
+        self.oracle.update(now, x, y);
+
+        let received = amount_received(mint_in, amount)?;
+        let (lp, _) = self.config.deposit_single(now, x, y, self.mint_lp.supply, is_x, received)?;
+        require!(lp > 0 && lp >= min_lp, AmmError::SlippageExceeded);
+        // the zap's internal swap moves the price to where the whole deposit leaves it
+        let (x1, y1) = match is_x {
+            true => (x + received, y),
+            false => (x, y + received),
+        };
+        self.config.check_price_band(self.reference_price.as_deref(), x1, y1)?;
+
+        self.deposit_token(is_x, amount)?;
+        self.deposit_lp(lp)?;
+        self.emit_deposit(lp)
+    }
+
+    // reports what reached the vaults by diffing them against the pre-instruction balances
+    fn emit_deposit(&mut self, lp_amount: u64) -> Result<()> {
+        let (before_x, before_y) = (self.vault_x.amount, self.vault_y.amount);
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        emit!(DepositEvent {
+            config: self.config.key(),
+            user: self.user.key(),
+            lp_amount,
+            amount_x: self.vault_x.amount - before_x,
+            amount_y: self.vault_y.amount - before_y,
+            reserve_x,
+            reserve_y,
+        });
+        Ok(())
+    }
+
+    fn deposit_token(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (to, mint, decimals, token_program) = match is_x {
+            true =>
+                (
+                    self.vault_x.to_account_info(),
+                    self.mint_x.to_account_info(),
+                    self.mint_x.decimals,
+                    self.token_program_x.to_account_info(),
+                ),
+            false =>
+                (
+                    self.vault_y.to_account_info(),
+                    self.mint_y.to_account_info(),
+                    self.mint_y.decimals,
+                    self.token_program_y.to_account_info(),
+                ),
+        };
+        let cpi_accounts = TransferChecked {
+            from: self.user_token.to_account_info(),
+            to,
+            mint,
+            authority: self.user.to_account_info(),
+        };
+        let ctx = CpiContext::new(token_program, cpi_accounts);
+        transfer_checked(ctx, amount, decimals)
+    }
+
+    fn deposit_lp(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = MintTo {
+            mint: self.mint_lp.to_account_info(),
+            to: self.user_lp.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+        let cpi_program = self.token_program.to_account_info();
+        let binding = self.config.seed.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[
+            &[b"config", binding.as_ref(), &[self.config.config_bump]],
+        ];
+        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        mint_to(ctx, amount)
+    }
+}
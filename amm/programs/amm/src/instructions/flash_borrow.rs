@@ -0,0 +1,121 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::instructions::{
+        load_current_index_checked,
+        load_instruction_at_checked,
+    },
+    Discriminator,
+};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{ transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked },
+};
+
+use crate::{ error::AmmError, state::{ Config, FlashLoan } };
+
+// position of `config` in the FlashRepay accounts
+const REPAY_CONFIG_INDEX: usize = 3;
+
+#[derive(Accounts)]
+pub struct FlashBorrow<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        has_one = mint_x,
+        has_one = mint_y
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    // receives the borrowed side
+    #[account(mut)]
+    pub user_token: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+      address = anchor_lang::solana_program::sysvar::instructions::ID
+    )]
+    /// CHECK: address checked against the instructions sysvar
+    pub instruction_sysvar: AccountInfo<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FlashBorrow<'info> {
+    pub fn flash_borrow(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        self.config.check_tradable()?;
+        self.verify_repay()?;
+        self.config.flash_loan = Some(FlashLoan { is_x, amount });
+        self.withdraw_tokens(is_x, amount)
+    }
+
+    // a top level flash_repay against this pool has to follow in the same transaction
+    pub fn verify_repay(&self) -> Result<()> {
+        let current = load_current_index_checked(&self.instruction_sysvar)? as usize;
+        let mut index = current + 1;
+        while let Ok(ix) = load_instruction_at_checked(index, &self.instruction_sysvar) {
+            if
+                ix.program_id == crate::ID &&
+                ix.data.starts_with(&crate::instruction::FlashRepay::DISCRIMINATOR) &&
+                ix.accounts
+                    .get(REPAY_CONFIG_INDEX)
+                    .is_some_and(|meta| meta.pubkey == self.config.key())
+            {
+                return Ok(());
+            }
+            index += 1;
+        }
+        err!(AmmError::MissingFlashRepay)
+    }
+
+    fn withdraw_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, mint, decimals, cpi_program) = match is_x {
+            true =>
+                (
+                    self.vault_x.to_account_info(),
+                    self.mint_x.to_account_info(),
+                    self.mint_x.decimals,
+                    self.token_program_x.to_account_info(),
+                ),
+            false =>
+                (
+                    self.vault_y.to_account_info(),
+                    self.mint_y.to_account_info(),
+                    self.mint_y.decimals,
+                    self.token_program_y.to_account_info(),
+                ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            to: self.user_token.to_account_info(),
+            mint,
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[&b"config"[..], &self.config.seed.to_le_bytes(), &[self.config.config_bump]];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        transfer_checked(cpi_ctx, amount, decimals)
+    }
+}
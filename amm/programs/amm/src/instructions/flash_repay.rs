@@ -0,0 +1,100 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{ transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked },
+};
+
+use crate::{ error::AmmError, events::FlashLoanEvent, state::Config, utils::amount_to_send };
+
+#[derive(Accounts)]
+pub struct FlashRepay<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    // FlashBorrow looks for this account at this position, keep it fourth
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        has_one = mint_x,
+        has_one = mint_y
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    // pays back the borrowed side
+    #[account(
+        mut,
+        token::authority = user,
+    )]
+    pub user_token: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FlashRepay<'info> {
+    // principal plus the pool fee, which stays in the vault for LPs
+    pub fn flash_repay(&mut self) -> Result<()> {
+        let loan = self.config.flash_loan.take().ok_or(AmmError::NoFlashLoan)?;
+        let fee_bps = self.config.current_fee(Clock::get()?.unix_timestamp);
+        let fee = ((loan.amount as u128) * (fee_bps as u128)).div_ceil(10_000) as u64;
+        let owed = loan.amount.checked_add(fee).ok_or(ProgramError::ArithmeticOverflow)?;
+        self.deposit_tokens(loan.is_x, owed)?;
+        emit!(FlashLoanEvent {
+            config: self.config.key(),
+            user: self.user.key(),
+            is_x: loan.is_x,
+            amount: loan.amount,
+            fee,
+        });
+        Ok(())
+    }
+
+    fn deposit_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (to, mint, decimals, cpi_program) = match is_x {
+            true =>
+                (
+                    self.vault_x.to_account_info(),
+                    &self.mint_x,
+                    self.mint_x.decimals,
+                    self.token_program_x.to_account_info(),
+                ),
+            false =>
+                (
+                    self.vault_y.to_account_info(),
+                    &self.mint_y,
+                    self.mint_y.decimals,
+                    self.token_program_y.to_account_info(),
+                ),
+        };
+        // the vault has to end up with `amount`, whatever the transfer fee takes
+        let amount = amount_to_send(mint, amount)?;
+
+        let cpi_accounts = TransferChecked {
+            from: self.user_token.to_account_info(),
+            to,
+            mint: mint.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        transfer_checked(cpi_ctx, amount, decimals)
+    }
+}
@@ -0,0 +1,64 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{ transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked },
+};
+
+use crate::{ events::FundFarmEvent, state::Farm, utils::amount_received };
+
+#[derive(Accounts)]
+pub struct FundFarm<'info> {
+    pub funder: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"farm", farm.config.as_ref()],
+        bump = farm.bump,
+        has_one = reward_mint
+    )]
+    pub farm: Account<'info, Farm>,
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = funder,
+        token::token_program = reward_token_program
+    )]
+    pub funder_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = reward_token_program
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FundFarm<'info> {
+    pub fn fund_farm(&mut self, amount: u64) -> Result<()> {
+        // accrue at the old funding level first so new rewards only count from now on
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+        let received = amount_received(&self.reward_mint, amount)?;
+        self.farm.undistributed = self.farm.undistributed
+            .checked_add(received)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        emit!(FundFarmEvent {
+            config: self.farm.config,
+            farm: self.farm.key(),
+            funder: self.funder.key(),
+            amount: received,
+            undistributed: self.farm.undistributed,
+        });
+
+        let cpi_accounts = TransferChecked {
+            from: self.funder_reward.to_account_info(),
+            to: self.reward_vault.to_account_info(),
+            mint: self.reward_mint.to_account_info(),
+            authority: self.funder.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.reward_token_program.to_account_info(), cpi_accounts);
+        transfer_checked(cpi_ctx, amount, self.reward_mint.decimals)
+    }
+}
@@ -1,38 +1,67 @@
 use anchor_lang::prelude::*;
-use anchor_spl::{ associated_token::AssociatedToken, token::{ Mint, Token, TokenAccount } };
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    metadata::{
+        create_metadata_accounts_v3,
+        mpl_token_metadata::types::DataV2,
+        CreateMetadataAccountsV3,
+        Metadata,
+    },
+    token_interface::{ Mint, TokenAccount, TokenInterface },
+};
 
-use crate::state::Config;
+use crate::{
+    constants::DEFAULT_OBSERVATION_INTERVAL,
+    curve::CurveType,
+    error::AmmError,
+    state::{ Config, Oracle, PoolEntry, PoolIndex, Registry },
+    utils::{ lp_symbol, token_symbol },
+};
 
 #[derive(Accounts)]
-#[instruction(seed:u64)]
+#[instruction(fee: u16, seed: u64)]
 pub struct Initialize<'info> {
     #[account(mut)]
     pub initializer: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         init,
         payer = initializer,
         seeds = [b"lp".as_ref(), config.key().as_ref()],
         bump,
         mint::decimals = 6,
-        mint::authority = config
+        mint::authority = config,
+        mint::token_program = token_program
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         init,
         payer = initializer,
         associated_token::mint = mint_x,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init,
         payer = initializer,
         associated_token::mint = mint_y,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    // holds the minimum liquidity locked by the first deposit
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_lp,
+        associated_token::authority = config,
+        associated_token::token_program = token_program
+    )]
+    pub locked_lp: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init,
         payer = initializer,
@@ -41,23 +70,174 @@ pub struct Initialize<'info> {
         space = 8 + Config::INIT_SPACE
     )]
     pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump,
+        space = 8 + Oracle::INIT_SPACE
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+    // init fails if the pair is already registered at this creation fee tier
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"pool", mint_x.key().as_ref(), mint_y.key().as_ref(), fee.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + PoolEntry::INIT_SPACE
+    )]
+    pub pool_entry: Box<Account<'info, PoolEntry>>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"pool", registry.pool_count.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + PoolIndex::INIT_SPACE
+    )]
+    pub pool_index: Box<Account<'info, PoolIndex>>,
+    // the metadata accounts are optional so pools can still be created on
+    // clusters without the Token Metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", Metadata::id().as_ref(), mint_lp.key().as_ref()],
+        bump,
+        seeds::program = Metadata::id()
+    )]
+    /// CHECK: created by the metadata program
+    pub lp_metadata: Option<UncheckedAccount<'info>>,
+    #[account(
+        seeds = [b"metadata", Metadata::id().as_ref(), mint_x.key().as_ref()],
+        bump,
+        seeds::program = Metadata::id()
+    )]
+    /// CHECK: only read, and only if it exists
+    pub metadata_x: Option<UncheckedAccount<'info>>,
+    #[account(
+        seeds = [b"metadata", Metadata::id().as_ref(), mint_y.key().as_ref()],
+        bump,
+        seeds::program = Metadata::id()
+    )]
+    /// CHECK: only read, and only if it exists
+    pub metadata_y: Option<UncheckedAccount<'info>>,
+    pub metadata_program: Option<Program<'info, Metadata>>,
+    pub rent: Sysvar<'info, Rent>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
 }
 
 impl<'info> Initialize<'info> {
-    pub fn initialize(&mut self, fee: u16, seed: u64, bumps: InitializeBumps) -> Result<()> {
+    pub fn initialize(
+        &mut self,
+        fee: u16,
+        seed: u64,
+        curve: CurveType,
+        bumps: InitializeBumps
+    ) -> Result<()> {
+        require!(fee < 10_000, AmmError::InvalidFee);
+        // canonical ordering, so Y/X can't be registered next to X/Y
+        require!(self.mint_x.key() < self.mint_y.key(), AmmError::MintsNotOrdered);
+        curve.validate()?;
         self.config.set_inner(Config {
             authority: Some(self.initializer.key()),
             seed: seed,
             fee: fee,
+            curve,
+            protocol_fee: 0,
+            referral_fee: 0,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
             mint_x: self.mint_x.key(),
             mint_y: self.mint_y.key(),
             locked: false,
+            flash_loan: None,
+            dynamic_fee: None,
+            allowlist: None,
+            allowlist_entries: 0,
+            has_farm: false,
+            price_band: None,
             config_bump: bumps.config,
             lp_bump: bumps.mint_lp,
         });
-        Ok(())
+        self.oracle.set_inner(
+            Oracle::init(
+                self.config.key(),
+                Clock::get()?.unix_timestamp,
+                DEFAULT_OBSERVATION_INTERVAL,
+                bumps.oracle
+            )
+        );
+        self.pool_entry.set_inner(PoolEntry {
+            config: self.config.key(),
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            fee,
+            index: self.registry.pool_count,
+            bump: bumps.pool_entry,
+        });
+        self.pool_index.set_inner(PoolIndex {
+            config: self.config.key(),
+            pool_entry: self.pool_entry.key(),
+            bump: bumps.pool_index,
+        });
+        self.registry.pool_count += 1;
+        self.create_lp_metadata()
+    }
+
+    // names the LP mint after the underlying symbols, with the config as update authority
+    pub fn create_lp_metadata(&self) -> Result<()> {
+        let (Some(metadata_program), Some(lp_metadata)) = (
+            &self.metadata_program,
+            &self.lp_metadata,
+        ) else {
+            return Ok(());
+        };
+        let symbol = lp_symbol(
+            &token_symbol(self.metadata_x.as_ref(), &self.mint_x.key()),
+            &token_symbol(self.metadata_y.as_ref(), &self.mint_y.key())
+        );
+
+        let cpi_accounts = CreateMetadataAccountsV3 {
+            metadata: lp_metadata.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            mint_authority: self.config.to_account_info(),
+            payer: self.initializer.to_account_info(),
+            update_authority: self.config.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+        };
+
+        let seeds = &[&b"config"[..], &self.config.seed.to_le_bytes(), &[self.config.config_bump]];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            metadata_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        create_metadata_accounts_v3(
+            cpi_ctx,
+            DataV2 {
+                name: format!("{} LP", symbol),
+                symbol,
+                uri: String::new(),
+                seller_fee_basis_points: 0,
+                creators: None,
+                collection: None,
+                uses: None,
+            },
+            true,
+            true,
+            None
+        )
     }
 }
@@ -1,8 +1,44 @@
 pub mod initialize;
+pub mod create_registry;
 pub mod deposit;
+pub mod deposit_single;
 pub mod swap;
+pub mod swap_route;
 pub mod withdraw;
+pub mod withdraw_single;
+pub mod update_config;
+pub mod collect_protocol_fees;
+pub mod update_lp_metadata;
+pub mod observe;
+pub mod update_oracle;
+pub mod flash_borrow;
+pub mod flash_repay;
+pub mod create_farm;
+pub mod fund_farm;
+pub mod stake_lp;
+pub mod claim;
+pub mod close_pool;
+pub mod add_to_allowlist;
+pub mod remove_from_allowlist;
 pub use initialize::*;
+pub use create_registry::*;
 pub use deposit::*;
+pub use deposit_single::*;
 pub use swap::*;
+pub use swap_route::*;
 pub use withdraw::*;
+pub use withdraw_single::*;
+pub use update_config::*;
+pub use collect_protocol_fees::*;
+pub use update_lp_metadata::*;
+pub use observe::*;
+pub use update_oracle::*;
+pub use flash_borrow::*;
+pub use flash_repay::*;
+pub use create_farm::*;
+pub use fund_farm::*;
+pub use stake_lp::*;
+pub use claim::*;
+pub use close_pool::*;
+pub use add_to_allowlist::*;
+pub use remove_from_allowlist::*;
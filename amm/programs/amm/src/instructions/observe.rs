@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
+
+use crate::{ error::AmmError, state::{ Config, Oracle, Twap } };
+
+#[derive(Accounts)]
+pub struct Observe<'info> {
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        has_one = mint_x,
+        has_one = mint_y
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+    #[account(
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> Observe<'info> {
+    // average prices over the last `seconds_ago` seconds, returned to CPI callers as return data
+    pub fn observe(&self, seconds_ago: u32) -> Result<Twap> {
+        require!(self.config.flash_loan.is_none(), AmmError::FlashLoanActive);
+        let (x, y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.oracle.twap(Clock::get()?.unix_timestamp, x, y, seconds_ago)
+    }
+}
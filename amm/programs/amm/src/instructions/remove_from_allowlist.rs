@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::{ error::AmmError, events::RemoveFromAllowlistEvent, state::{ AllowlistEntry, Config } };
+
+#[derive(Accounts)]
+pub struct RemoveFromAllowlist<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"allowlist", config.key().as_ref(), allowlist_entry.user.as_ref()],
+        bump = allowlist_entry.bump
+    )]
+    pub allowlist_entry: Account<'info, AllowlistEntry>,
+}
+
+impl<'info> RemoveFromAllowlist<'info> {
+    // the rent goes back to the authority
+    pub fn remove_from_allowlist(&mut self) -> Result<()> {
+        self.config.allowlist_entries = self.config.allowlist_entries
+            .checked_sub(1)
+            .ok_or(AmmError::MathOverflow)?;
+        emit!(RemoveFromAllowlistEvent {
+            config: self.config.key(),
+            user: self.allowlist_entry.user,
+        });
+        self.allowlist_entry.close(self.authority.to_account_info())
+    }
+}
@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{ transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked },
+};
+
+use crate::{
+    error::AmmError,
+    events::{ StakeEvent, UnstakeEvent },
+    state::{ Config, Farm, UserStake },
+};
+
+#[derive(Accounts)]
+pub struct StakeLp<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = token_program
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump
+    )]
+    pub farm: Account<'info, Farm>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"stake", farm.key().as_ref(), user.key().as_ref()],
+        bump,
+        space = 8 + UserStake::INIT_SPACE
+    )]
+    pub user_stake: Account<'info, UserStake>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program
+    )]
+    pub lp_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> StakeLp<'info> {
+    pub fn stake_lp(&mut self, amount: u64, bumps: StakeLpBumps) -> Result<()> {
+        if self.user_stake.farm == Pubkey::default() {
+            self.user_stake.set_inner(UserStake {
+                farm: self.farm.key(),
+                owner: self.user.key(),
+                amount: 0,
+                reward_debt: 0,
+                pending: 0,
+                bump: bumps.user_stake,
+            });
+        }
+        self.settle()?;
+        self.user_stake.amount = self.user_stake.amount
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.farm.total_staked = self.farm.total_staked
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.user_stake.reset_debt(&self.farm);
+        emit!(StakeEvent {
+            config: self.config.key(),
+            farm: self.farm.key(),
+            user: self.user.key(),
+            amount,
+            total_staked: self.farm.total_staked,
+        });
+
+        let cpi_accounts = TransferChecked {
+            from: self.user_lp.to_account_info(),
+            to: self.lp_vault.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer_checked(cpi_ctx, amount, self.mint_lp.decimals)
+    }
+
+    pub fn unstake_lp(&mut self, amount: u64) -> Result<()> {
+        require!(amount <= self.user_stake.amount, AmmError::InsufficientStake);
+        self.settle()?;
+        self.user_stake.amount -= amount;
+        self.farm.total_staked -= amount;
+        self.user_stake.reset_debt(&self.farm);
+        emit!(UnstakeEvent {
+            config: self.config.key(),
+            farm: self.farm.key(),
+            user: self.user.key(),
+            amount,
+            total_staked: self.farm.total_staked,
+        });
+
+        let cpi_accounts = TransferChecked {
+            from: self.lp_vault.to_account_info(),
+            to: self.user_lp.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            authority: self.farm.to_account_info(),
+        };
+        let config_key = self.config.key();
+        let seeds = &[&b"farm"[..], config_key.as_ref(), &[self.farm.bump]];
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+        transfer_checked(cpi_ctx, amount, self.mint_lp.decimals)
+    }
+
+    fn settle(&mut self) -> Result<()> {
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+        self.user_stake.settle(&self.farm)
+    }
+}
@@ -1,106 +1,256 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{ Mint, Token, TokenAccount, Transfer, transfer },
+    token_interface::{ transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked },
+};
+
+use constant_product_curve::SwapResult;
+
+use crate::{
+    error::AmmError,
+    events::SwapEvent,
+    state::{ post_swap_reserves, AllowlistEntry, Config, Oracle },
+    utils::{ amount_received, amount_to_send },
 };
-use constant_product_curve::{ ConstantProduct, LiquidityPair };
 
-use crate::state::Config;
 #[derive(Accounts)]
 pub struct Swap<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_x,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program_x
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_y,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program_y
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_x,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_y,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
+        mut,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
         bump = config.config_bump,
         has_one = mint_x,
         has_one = mint_y
     )]
     pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+    // only needed on permissioned pools
+    #[account(
+        seeds = [b"allowlist", config.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_entry.bump
+    )]
+    pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+    // gets config.referral_fee of the swap fee, has to hold the input token
+    #[account(mut)]
+    pub referrer: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// CHECK: only read, checked against config.price_band
+    pub reference_price: Option<UncheckedAccount<'info>>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
 }
 
 impl<'info> Swap<'info> {
     pub fn swap(&mut self, is_x: bool, amount: u64, min: u64) -> Result<()> {
-        let mut curve = ConstantProduct::init(
-            self.vault_x.amount,
-            self.vault_y.amount,
-            self.vault_x.amount,
-            self.config.fee,
-            None
-        ).unwrap();
-        let p = match is_x {
-            true => LiquidityPair::X,
-            false => LiquidityPair::Y,
+        self.config.check_tradable()?;
+        self.config.check_trader(self.allowlist_entry.as_deref())?;
+        let now = Clock::get()?.unix_timestamp;
+        let (x, y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.oracle.update(now, x, y);
+        let (mint_in, mint_out) = match is_x {
+            true => (&self.mint_x, &self.mint_y),
+            false => (&self.mint_y, &self.mint_x),
         };
-        let res = curve.swap(p, amount, min).unwrap();
-        self.deposit_tokens(is_x, res.deposit)?;
+        // price the swap on what reaches the vault and check slippage on what reaches the user
+        let received = amount_received(mint_in, amount)?;
+        let res = self.config.swap(now, x, y, is_x, received)?;
+        self.check_price_band(x, y, is_x, &res)?;
+        require!(amount_received(mint_out, res.withdraw)? >= min, AmmError::SlippageExceeded);
+        self.deposit_tokens(is_x, amount)?;
         // withdraw tokens
         self.withdraw_tokens(is_x, res.withdraw)?;
+        let referral = self.pay_referrer(is_x, res.fee)?;
+        self.emit_swap(is_x, &res, referral)
+    }
+
+    pub fn swap_exact_out(&mut self, is_x: bool, amount_out: u64, max_in: u64) -> Result<()> {
+        self.config.check_tradable()?;
+        self.config.check_trader(self.allowlist_entry.as_deref())?;
+        let now = Clock::get()?.unix_timestamp;
+        let (x, y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.oracle.update(now, x, y);
+        let (mint_in, mint_out) = match is_x {
+            true => (&self.mint_x, &self.mint_y),
+            false => (&self.mint_y, &self.mint_x),
+        };
+        // the user gets exactly `amount_out`, so gross both legs up for transfer fees
+        let withdraw = amount_to_send(mint_out, amount_out)?;
+        let res = self.config.swap_exact_out(now, x, y, is_x, withdraw)?;
+        self.check_price_band(x, y, is_x, &res)?;
+        let amount_in = amount_to_send(mint_in, res.deposit)?;
+        require!(amount_in <= max_in, AmmError::SlippageExceeded);
+        self.deposit_tokens(is_x, amount_in)?;
+        self.withdraw_tokens(is_x, res.withdraw)?;
+        let referral = self.pay_referrer(is_x, res.fee)?;
+        self.emit_swap(is_x, &res, referral)
+    }
+
+    fn check_price_band(&self, x: u64, y: u64, is_x: bool, res: &SwapResult) -> Result<()> {
+        let (x, y) = post_swap_reserves(x, y, is_x, res);
+        self.config.check_price_band(self.reference_price.as_deref(), x, y)
+    }
+
+    // the referrer's cut of the fee leaves the input vault, so it comes out of the LPs' share
+    fn pay_referrer(&self, is_x: bool, fee: u64) -> Result<u64> {
+        let Some(referrer) = &self.referrer else {
+            return Ok(0);
+        };
+        let (vault, mint, cpi_program) = match is_x {
+            true => (&self.vault_x, &self.mint_x, &self.token_program_x),
+            false => (&self.vault_y, &self.mint_y, &self.token_program_y),
+        };
+        require_keys_eq!(referrer.mint, mint.key(), AmmError::InvalidTokenAccount);
+        // a trader naming their own account would just get a discount on the LPs' fee
+        require_keys_neq!(referrer.owner, self.user.key(), AmmError::SelfReferral);
+        let amount = self.config.referral_cut(fee);
+        if amount == 0 {
+            return Ok(0);
+        }
+
+        let accounts = TransferChecked {
+            from: vault.to_account_info(),
+            to: referrer.to_account_info(),
+            mint: mint.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[&b"config"[..], &self.config.seed.to_le_bytes(), &[self.config.config_bump]];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            cpi_program.to_account_info(),
+            accounts,
+            signer_seeds
+        );
+
+        transfer_checked(cpi_ctx, amount, mint.decimals)?;
+
+        Ok(amount)
+    }
+
+    fn emit_swap(&mut self, is_x: bool, res: &SwapResult, referral_fee: u64) -> Result<()> {
+        let (before_x, before_y) = (self.vault_x.amount, self.vault_y.amount);
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        let (amount_in, amount_out) = match is_x {
+            true => (self.vault_x.amount + referral_fee - before_x, before_y - self.vault_y.amount),
+            false => (self.vault_y.amount + referral_fee - before_y, before_x - self.vault_x.amount),
+        };
+        emit!(SwapEvent {
+            config: self.config.key(),
+            user: self.user.key(),
+            is_x,
+            amount_in,
+            amount_out,
+            fee: res.fee,
+            referral_fee,
+            reserve_x,
+            reserve_y,
+        });
         Ok(())
     }
+
     pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
-            true => (self.user_x.to_account_info(), self.vault_x.to_account_info()),
-            false => (self.user_y.to_account_info(), self.vault_y.to_account_info()),
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true =>
+                (
+                    self.user_x.to_account_info(),
+                    self.vault_x.to_account_info(),
+                    self.mint_x.to_account_info(),
+                    self.mint_x.decimals,
+                    self.token_program_x.to_account_info(),
+                ),
+            false =>
+                (
+                    self.user_y.to_account_info(),
+                    self.vault_y.to_account_info(),
+                    self.mint_y.to_account_info(),
+                    self.mint_y.decimals,
+                    self.token_program_y.to_account_info(),
+                ),
         };
 
-        let cpi_program = self.token_program.to_account_info();
-
-        let accounts = Transfer {
-            from: from.to_account_info(),
-            to: to.to_account_info(),
+        let accounts = TransferChecked {
+            from,
+            to,
+            mint,
             authority: self.user.to_account_info(),
         };
 
         let cpi_ctx = CpiContext::new(cpi_program, accounts);
 
-        transfer(cpi_ctx, amount)?;
+        transfer_checked(cpi_ctx, amount, decimals)?;
 
         Ok(())
     }
 
     pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
-            true => (self.vault_y.to_account_info(), self.user_y.to_account_info()),
-            false => (self.vault_x.to_account_info(), self.user_x.to_account_info()),
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true =>
+                (
+                    self.vault_y.to_account_info(),
+                    self.user_y.to_account_info(),
+                    self.mint_y.to_account_info(),
+                    self.mint_y.decimals,
+                    self.token_program_y.to_account_info(),
+                ),
+            false =>
+                (
+                    self.vault_x.to_account_info(),
+                    self.user_x.to_account_info(),
+                    self.mint_x.to_account_info(),
+                    self.mint_x.decimals,
+                    self.token_program_x.to_account_info(),
+                ),
         };
 
-        let cpi_program = self.token_program.to_account_info();
-
-        let accounts = Transfer {
-            from: from.to_account_info(),
-            to: to.to_account_info(),
+        let accounts = TransferChecked {
+            from,
+            to,
+            mint,
             authority: self.config.to_account_info(),
         };
 
@@ -109,7 +259,7 @@ impl<'info> Swap<'info> {
 
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, accounts, signer_seeds);
 
-        transfer(cpi_ctx, amount)?;
+        transfer_checked(cpi_ctx, amount, decimals)?;
 
         Ok(())
     }
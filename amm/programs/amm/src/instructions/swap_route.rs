@@ -0,0 +1,249 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{ get_associated_token_address_with_program_id, AssociatedToken },
+    token_interface::{ transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked },
+};
+
+use crate::{ error::AmmError, events::SwapEvent, state::{ Config, Oracle }, utils::amount_received };
+
+// config, mint_x, mint_y, vault_x, vault_y, token_program_x, token_program_y, oracle
+pub const HOP_ACCOUNTS: usize = 8;
+
+#[derive(Accounts)]
+pub struct SwapRoute<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mint::token_program = token_program_in)]
+    pub mint_in: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_out)]
+    pub mint_out: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        token::mint = mint_in,
+        token::authority = user,
+        token::token_program = token_program_in
+    )]
+    pub user_in: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_out,
+        associated_token::authority = user,
+        associated_token::token_program = token_program_out
+    )]
+    pub user_out: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program_in: Interface<'info, TokenInterface>,
+    pub token_program_out: Interface<'info, TokenInterface>,
+}
+
+// (mint, vault, token program) of one side of a pool
+type Side<'a, 'info> = (&'a InterfaceAccount<'info, Mint>, AccountInfo<'info>, AccountInfo<'info>);
+
+// one pool of the route, loaded from remaining_accounts
+struct Hop<'info> {
+    config: Account<'info, Config>,
+    oracle: Box<Account<'info, Oracle>>,
+    mint_x: InterfaceAccount<'info, Mint>,
+    mint_y: InterfaceAccount<'info, Mint>,
+    vault_x: InterfaceAccount<'info, TokenAccount>,
+    vault_y: InterfaceAccount<'info, TokenAccount>,
+    token_program_x: &'info AccountInfo<'info>,
+    token_program_y: &'info AccountInfo<'info>,
+}
+
+impl<'info> Hop<'info> {
+    fn load(accounts: &'info [AccountInfo<'info>]) -> Result<Self> {
+        require!(accounts[0].is_writable, AmmError::InvalidRoute);
+        let config = Account::<Config>::try_from(&accounts[0])?;
+        let expected = Pubkey::create_program_address(
+            &[b"config", config.seed.to_le_bytes().as_ref(), &[config.config_bump]],
+            &crate::ID
+        ).map_err(|_| AmmError::InvalidRoute)?;
+        require_keys_eq!(config.key(), expected, AmmError::InvalidRoute);
+
+        let mint_x = InterfaceAccount::<Mint>::try_from(&accounts[1])?;
+        let mint_y = InterfaceAccount::<Mint>::try_from(&accounts[2])?;
+        require_keys_eq!(mint_x.key(), config.mint_x, AmmError::InvalidRoute);
+        require_keys_eq!(mint_y.key(), config.mint_y, AmmError::InvalidRoute);
+
+        let (token_program_x, token_program_y) = (&accounts[5], &accounts[6]);
+        require_keys_eq!(*accounts[1].owner, token_program_x.key(), AmmError::InvalidRoute);
+        require_keys_eq!(*accounts[2].owner, token_program_y.key(), AmmError::InvalidRoute);
+
+        let vault_x = InterfaceAccount::<TokenAccount>::try_from(&accounts[3])?;
+        let vault_y = InterfaceAccount::<TokenAccount>::try_from(&accounts[4])?;
+        require_keys_eq!(
+            vault_x.key(),
+            get_associated_token_address_with_program_id(
+                &config.key(),
+                &mint_x.key(),
+                &token_program_x.key()
+            ),
+            AmmError::InvalidRoute
+        );
+        require_keys_eq!(
+            vault_y.key(),
+            get_associated_token_address_with_program_id(
+                &config.key(),
+                &mint_y.key(),
+                &token_program_y.key()
+            ),
+            AmmError::InvalidRoute
+        );
+
+        require!(accounts[7].is_writable, AmmError::InvalidRoute);
+        let oracle = Box::new(Account::<Oracle>::try_from(&accounts[7])?);
+        require_keys_eq!(oracle.config, config.key(), AmmError::InvalidRoute);
+
+        Ok(Hop { config, oracle, mint_x, mint_y, vault_x, vault_y, token_program_x, token_program_y })
+    }
+
+    // input and output side of a swap in the given direction
+    fn sides(&self, is_x: bool) -> (Side<'_, 'info>, Side<'_, 'info>) {
+        let x = (&self.mint_x, self.vault_x.to_account_info(), self.token_program_x.clone());
+        let y = (&self.mint_y, self.vault_y.to_account_info(), self.token_program_y.clone());
+        match is_x {
+            true => (x, y),
+            false => (y, x),
+        }
+    }
+}
+
+impl<'info> SwapRoute<'info> {
+    pub fn swap_route(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        amount_in: u64,
+        min_out: u64
+    ) -> Result<()> {
+        let hops = remaining_accounts.chunks_exact(HOP_ACCOUNTS);
+        require!(
+            !remaining_accounts.is_empty() && hops.remainder().is_empty(),
+            AmmError::InvalidRoute
+        );
+
+        let mut mint = self.mint_in.key();
+        let mut amount = amount_in;
+        // where the tokens for the current hop come from: the user for the first hop,
+        // the previous pool's output vault afterwards
+        let mut source = self.user_in.to_account_info();
+        let mut source_authority = self.user.to_account_info();
+        let mut source_seeds: Option<(u64, u8)> = None;
+        let mut previous: Option<Pubkey> = None;
+
+        for accounts in hops {
+            let mut hop = Hop::load(accounts)?;
+            // a hop's output only leaves its vault on the next hop, so the same pool again
+            // would price against reserves that still hold it
+            require!(previous != Some(hop.config.key()), AmmError::RepeatedPool);
+            previous = Some(hop.config.key());
+            hop.config.check_tradable()?;
+            // there is no slot for allowlist entries in a hop, so gated pools can't be routed
+            hop.config.check_trader(None)?;
+            // same for the reference price of banded pools
+            hop.config.check_price_band(None, 0, 0)?;
+            let is_x = match mint {
+                m if m == hop.config.mint_x => true,
+                m if m == hop.config.mint_y => false,
+                _ => return err!(AmmError::InvalidRoute),
+            };
+
+            let (x, y) = hop.config.reserves(hop.vault_x.amount, hop.vault_y.amount)?;
+            let now = Clock::get()?.unix_timestamp;
+            hop.oracle.update(now, x, y);
+            let ((mint_in, vault_in, program_in), (mint_out, vault_out, _)) = hop.sides(is_x);
+            let received = amount_received(mint_in, amount)?;
+            let (mint_in, mint_out) = (mint_in.clone(), mint_out.clone());
+            let res = hop.config.swap(now, x, y, is_x, received)?;
+            // the output only leaves the vault on the next hop, so work the reserves out here
+            let (reserve_x, reserve_y) = match is_x {
+                true =>
+                    hop.config.reserves(
+                        hop.vault_x.amount + res.deposit,
+                        hop.vault_y.amount - res.withdraw
+                    )?,
+                false =>
+                    hop.config.reserves(
+                        hop.vault_x.amount - res.withdraw,
+                        hop.vault_y.amount + res.deposit
+                    )?,
+            };
+            emit!(SwapEvent {
+                config: hop.config.key(),
+                user: self.user.key(),
+                is_x,
+                amount_in: res.deposit,
+                amount_out: res.withdraw,
+                fee: res.fee,
+                referral_fee: 0,
+                reserve_x,
+                reserve_y,
+            });
+
+            let config = hop.config.to_account_info();
+            transfer(
+                source,
+                vault_in,
+                &mint_in,
+                source_authority,
+                program_in,
+                amount,
+                source_seeds
+            )?;
+            hop.config.exit(&crate::ID)?;
+            hop.oracle.exit(&crate::ID)?;
+
+            mint = mint_out.key();
+            amount = res.withdraw;
+            source = vault_out;
+            source_authority = config;
+            source_seeds = Some((hop.config.seed, hop.config.config_bump));
+        }
+
+        require_keys_eq!(mint, self.mint_out.key(), AmmError::InvalidRoute);
+        require!(
+            amount_received(&self.mint_out, amount)? >= min_out,
+            AmmError::SlippageExceeded
+        );
+        transfer(
+            source,
+            self.user_out.to_account_info(),
+            &self.mint_out,
+            source_authority,
+            self.token_program_out.to_account_info(),
+            amount,
+            source_seeds
+        )
+    }
+}
+
+fn transfer<'info>(
+    from: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+    config_seeds: Option<(u64, u8)>
+) -> Result<()> {
+    let cpi_accounts = TransferChecked {
+        from,
+        to,
+        mint: mint.to_account_info(),
+        authority,
+    };
+    match config_seeds {
+        Some((seed, bump)) => {
+            let seeds = &[&b"config"[..], &seed.to_le_bytes(), &[bump]];
+            let signer_seeds = &[&seeds[..]];
+            let cpi_ctx = CpiContext::new_with_signer(token_program, cpi_accounts, signer_seeds);
+            transfer_checked(cpi_ctx, amount, mint.decimals)
+        }
+        None => {
+            let cpi_ctx = CpiContext::new(token_program, cpi_accounts);
+            transfer_checked(cpi_ctx, amount, mint.decimals)
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::AmmError,
+    events::UpdateConfigEvent,
+    state::{ AllowlistMode, Config, DynamicFee, PriceBand },
+};
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    // the pool stays registered under the fee it was created with, see PoolEntry
+    pub fn update_fee(&mut self, fee: u16) -> Result<()> {
+        require!(fee < 10_000, AmmError::InvalidFee);
+        self.config.fee = fee;
+        self.emit_update()
+    }
+
+    // switches to a fee between min_fee and max_fee that tracks recent volatility
+    pub fn enable_dynamic_fee(
+        &mut self,
+        min_fee: u16,
+        max_fee: u16,
+        sensitivity: u16,
+        decay_period: u32
+    ) -> Result<()> {
+        require!(min_fee <= max_fee && max_fee < 10_000, AmmError::InvalidFee);
+        require!(decay_period > 0, AmmError::InvalidDecayPeriod);
+        self.config.dynamic_fee = Some(DynamicFee {
+            min_fee,
+            max_fee,
+            sensitivity,
+            decay_period,
+            volatility: 0,
+            last_update: Clock::get()?.unix_timestamp,
+        });
+        self.emit_update()
+    }
+
+    pub fn disable_dynamic_fee(&mut self) -> Result<()> {
+        self.config.dynamic_fee = None;
+        self.emit_update()
+    }
+
+    // protocol and referral shares come out of the same fee, so together they can't exceed it
+    pub fn update_protocol_fee(&mut self, protocol_fee: u16) -> Result<()> {
+        require!(
+            (protocol_fee as u32) + (self.config.referral_fee as u32) <= 10_000,
+            AmmError::InvalidProtocolFee
+        );
+        self.config.protocol_fee = protocol_fee;
+        self.emit_update()
+    }
+
+    // None makes the pool permissionless again, entries are kept for later
+    pub fn set_allowlist(&mut self, allowlist: Option<AllowlistMode>) -> Result<()> {
+        self.config.allowlist = allowlist;
+        self.emit_update()
+    }
+
+    pub fn update_referral_fee(&mut self, referral_fee: u16) -> Result<()> {
+        require!(
+            (referral_fee as u32) + (self.config.protocol_fee as u32) <= 10_000,
+            AmmError::InvalidReferralFee
+        );
+        self.config.referral_fee = referral_fee;
+        self.emit_update()
+    }
+
+    pub fn set_price_band(&mut self, price_band: Option<PriceBand>) -> Result<()> {
+        self.config.price_band = price_band;
+        self.emit_update()
+    }
+
+    pub fn lock(&mut self) -> Result<()> {
+        self.config.locked = true;
+        self.emit_update()
+    }
+
+    pub fn unlock(&mut self) -> Result<()> {
+        self.config.locked = false;
+        self.emit_update()
+    }
+
+    pub fn transfer_authority(&mut self, new_authority: Pubkey) -> Result<()> {
+        self.config.authority = Some(new_authority);
+        self.emit_update()
+    }
+
+    // once renounced the fee and lock state are frozen for good
+    pub fn renounce_authority(&mut self) -> Result<()> {
+        self.config.authority = None;
+        self.emit_update()
+    }
+
+    fn emit_update(&self) -> Result<()> {
+        emit!(UpdateConfigEvent {
+            config: self.config.key(),
+            authority: self.config.authority,
+            fee: self.config.fee,
+            dynamic_fee: self.config.dynamic_fee,
+            protocol_fee: self.config.protocol_fee,
+            referral_fee: self.config.referral_fee,
+            allowlist: self.config.allowlist,
+            price_band: self.config.price_band,
+            locked: self.config.locked,
+        });
+        Ok(())
+    }
+}
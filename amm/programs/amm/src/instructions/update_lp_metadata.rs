@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    metadata::{
+        mpl_token_metadata::types::DataV2,
+        update_metadata_accounts_v2,
+        Metadata,
+        MetadataAccount,
+        UpdateMetadataAccountsV2,
+    },
+    token_interface::Mint,
+};
+
+use crate::{ error::AmmError, events::UpdateLpMetadataEvent, state::Config };
+
+#[derive(Accounts)]
+pub struct UpdateLpMetadata<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"metadata", metadata_program.key().as_ref(), mint_lp.key().as_ref()],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    pub lp_metadata: Box<Account<'info, MetadataAccount>>,
+    pub metadata_program: Program<'info, Metadata>,
+}
+
+impl<'info> UpdateLpMetadata<'info> {
+    pub fn update_lp_metadata(&mut self, name: String, symbol: String, uri: String) -> Result<()> {
+        let cpi_accounts = UpdateMetadataAccountsV2 {
+            metadata: self.lp_metadata.to_account_info(),
+            update_authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[&b"config"[..], &self.config.seed.to_le_bytes(), &[self.config.config_bump]];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.metadata_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        update_metadata_accounts_v2(
+            cpi_ctx,
+            None,
+            Some(DataV2 {
+                name: name.clone(),
+                symbol: symbol.clone(),
+                uri: uri.clone(),
+                seller_fee_basis_points: 0,
+                creators: None,
+                collection: None,
+                uses: None,
+            }),
+            None,
+            None
+        )?;
+
+        emit!(UpdateLpMetadataEvent { config: self.config.key(), name, symbol, uri });
+        Ok(())
+    }
+}
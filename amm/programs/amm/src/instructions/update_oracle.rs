@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::{ error::AmmError, events::SetObservationIntervalEvent, state::{ Config, Oracle } };
+
+#[derive(Accounts)]
+pub struct UpdateOracle<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+}
+
+impl<'info> UpdateOracle<'info> {
+    // longer intervals reach further back at a coarser resolution, observations already
+    // stored keep their spacing
+    pub fn set_observation_interval(&mut self, interval: u32) -> Result<()> {
+        require!(interval > 0, AmmError::InvalidObservationInterval);
+        self.oracle.interval = interval;
+        emit!(SetObservationIntervalEvent { config: self.config.key(), interval });
+        Ok(())
+    }
+}
@@ -1,96 +1,217 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{ transfer, Burn, Mint, Token, TokenAccount, Transfer, burn },
+    token_interface::{
+        burn,
+        transfer_checked,
+        Burn,
+        Mint,
+        TokenAccount,
+        TokenInterface,
+        TransferChecked,
+    },
 };
-use constant_product_curve::ConstantProduct;
 
-use crate::state::Config;
+use crate::{
+    error::AmmError,
+    events::WithdrawEvent,
+    state::{ AllowlistEntry, Config, Oracle },
+    utils::{ amount_received, amount_to_send },
+};
 
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    // mut for withdraw_imbalanced, which feeds the dynamic fee's volatility
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
         bump = config.config_bump
     )]
     pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
     #[account(
         mut,
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
+        mint::token_program = token_program
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = config,
+        associated_token::token_program = token_program_x
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = config,
+        associated_token::token_program = token_program_y
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_x,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program_x
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_y,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program_y
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_lp,
-        associated_token::authority = config,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    // only read by withdraw_imbalanced when swaps are permissioned, plain withdrawals stay open
+    #[account(
+        seeds = [b"allowlist", config.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_entry.bump
     )]
-    pub user_lp: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+    /// CHECK: only read, checked against config.price_band by withdraw_imbalanced
+    pub reference_price: Option<UncheckedAccount<'info>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
 impl<'info> Withdraw<'info> {
-    pub fn withdraw(&mut self, amount: u64, _min_x: u64, _min_y: u64) -> Result<()> {
-        let amounts = ConstantProduct::xy_withdraw_amounts_from_l(
+    pub fn withdraw(&mut self, amount: u64, min_x: u64, min_y: u64) -> Result<()> {
+        self.config.check_tradable()?;
+        let (reserve_x, reserve_y) = self.config.reserves(
             self.vault_x.amount,
-            self.vault_y.amount,
+            self.vault_y.amount
+        )?;
+        self.oracle.update(Clock::get()?.unix_timestamp, reserve_x, reserve_y);
+        let amounts = self.config.withdraw_amounts(
+            reserve_x,
+            reserve_y,
             self.mint_lp.supply,
-            amount,
-            6
-        ).unwrap();
+            amount
+        )?;
+        require!(
+            amount_received(&self.mint_x, amounts.x)? >= min_x &&
+                amount_received(&self.mint_y, amounts.y)? >= min_y,
+            AmmError::SlippageExceeded
+        );
 
         self.withdraw_tokens(true, amounts.x)?;
         self.withdraw_tokens(false, amounts.y)?;
         self.burn_lp_tokens(amount)?;
+        self.emit_withdraw(amount)
+    }
 
+    // pays out exactly `amount_x` and `amount_y` and burns whatever LP that costs. Anything
+    // off the pool's ratio is a swap, so it needs the same permission as one
+    pub fn withdraw_imbalanced(&mut self, amount_x: u64, amount_y: u64, max_lp: u64) -> Result<()> {
+        self.config.check_tradable()?;
+        self.config.check_trader(self.allowlist_entry.as_deref())?;
+        let (reserve_x, reserve_y) = self.config.reserves(
+            self.vault_x.amount,
+            self.vault_y.amount
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        self.oracle.update(now, reserve_x, reserve_y);
+
+        let (x, y) = (
+            amount_to_send(&self.mint_x, amount_x)?,
+            amount_to_send(&self.mint_y, amount_y)?,
+        );
+        let lp = self.config.curve.withdraw_imbalanced_lp(
+            reserve_x,
+            reserve_y,
+            self.config.current_fee(now),
+            self.mint_lp.supply,
+            x,
+            y
+        )?;
+        require!(lp <= max_lp, AmmError::SlippageExceeded);
+        // the part of the payout that is off the pool's ratio moves the price like a swap
+        self.config.check_price_band(
+            self.reference_price.as_deref(),
+            reserve_x - x,
+            reserve_y - y
+        )?;
+        self.config.record_move(now, reserve_x, reserve_y, reserve_x - x, reserve_y - y);
+
+        if x > 0 {
+            self.withdraw_tokens(true, x)?;
+        }
+        if y > 0 {
+            self.withdraw_tokens(false, y)?;
+        }
+        self.burn_lp_tokens(lp)?;
+        self.emit_withdraw(lp)
+    }
+
+    // reports what left the vaults by diffing them against the pre-instruction balances
+    fn emit_withdraw(&mut self, lp_amount: u64) -> Result<()> {
+        let (before_x, before_y) = (self.vault_x.amount, self.vault_y.amount);
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        emit!(WithdrawEvent {
+            config: self.config.key(),
+            user: self.user.key(),
+            lp_amount,
+            amount_x: before_x - self.vault_x.amount,
+            amount_y: before_y - self.vault_y.amount,
+            reserve_x,
+            reserve_y,
+        });
         Ok(())
     }
 
     pub fn withdraw_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
-            true => (self.vault_x.to_account_info(), self.user_x.to_account_info()),
-            false => (self.vault_y.to_account_info(), self.user_y.to_account_info()),
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true =>
+                (
+                    self.vault_x.to_account_info(),
+                    self.user_x.to_account_info(),
+                    self.mint_x.to_account_info(),
+                    self.mint_x.decimals,
+                    self.token_program_x.to_account_info(),
+                ),
+            false =>
+                (
+                    self.vault_y.to_account_info(),
+                    self.user_y.to_account_info(),
+                    self.mint_y.to_account_info(),
+                    self.mint_y.decimals,
+                    self.token_program_y.to_account_info(),
+                ),
         };
 
-        let cpi_program = self.token_program.to_account_info();
-
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from,
             to,
+            mint,
             authority: self.config.to_account_info(),
         };
 
@@ -99,7 +220,7 @@ impl<'info> Withdraw<'info> {
 
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
 
-        transfer(cpi_ctx, amount)?;
+        transfer_checked(cpi_ctx, amount, decimals)?;
 
         Ok(())
     }
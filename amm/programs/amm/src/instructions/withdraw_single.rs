@@ -0,0 +1,180 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        burn,
+        transfer_checked,
+        Burn,
+        Mint,
+        TokenAccount,
+        TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{
+    error::AmmError,
+    events::WithdrawEvent,
+    state::{ AllowlistEntry, Config, Oracle },
+    utils::amount_received,
+};
+
+#[derive(Accounts)]
+pub struct WithdrawSingle<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+    #[account(
+        mut,
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = token_program
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    // the user's account for whichever side is withdrawn
+    #[account(mut)]
+    pub user_token: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    // only needed when swaps are permissioned, the zap swaps one side into the other
+    #[account(
+        seeds = [b"allowlist", config.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_entry.bump
+    )]
+    pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+    /// CHECK: only read, checked against config.price_band
+    pub reference_price: Option<UncheckedAccount<'info>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> WithdrawSingle<'info> {
+    pub fn withdraw_single(&mut self, is_x: bool, amount: u64, min_out: u64) -> Result<()> {
+        self.config.check_tradable()?;
+        self.config.check_trader(self.allowlist_entry.as_deref())?;
+        let mint_out = match is_x {
+            true => &self.mint_x,
+            false => &self.mint_y,
+        };
+        require_keys_eq!(self.user_token.mint, mint_out.key(), AmmError::InvalidTokenAccount);
+
+        let (x, y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        let now = Clock::get()?.unix_timestamp;
+        self.oracle.update(now, x, y);
+
+        let (out, _) = self.config.withdraw_single(now, x, y, self.mint_lp.supply, is_x, amount)?;
+        require!(amount_received(mint_out, out)? >= min_out, AmmError::SlippageExceeded);
+        // likewise the price ends up where paying everything out of one side leaves it
+        let (x1, y1) = match is_x {
+            true => (x - out, y),
+            false => (x, y - out),
+        };
+        self.config.check_price_band(self.reference_price.as_deref(), x1, y1)?;
+
+        self.withdraw_tokens(is_x, out)?;
+        self.burn_lp_tokens(amount)?;
+        self.emit_withdraw(amount)
+    }
+
+    // reports what left the vaults by diffing them against the pre-instruction balances
+    fn emit_withdraw(&mut self, lp_amount: u64) -> Result<()> {
+        let (before_x, before_y) = (self.vault_x.amount, self.vault_y.amount);
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        emit!(WithdrawEvent {
+            config: self.config.key(),
+            user: self.user.key(),
+            lp_amount,
+            amount_x: before_x - self.vault_x.amount,
+            amount_y: before_y - self.vault_y.amount,
+            reserve_x,
+            reserve_y,
+        });
+        Ok(())
+    }
+
+    fn withdraw_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, mint, decimals, cpi_program) = match is_x {
+            true =>
+                (
+                    self.vault_x.to_account_info(),
+                    self.mint_x.to_account_info(),
+                    self.mint_x.decimals,
+                    self.token_program_x.to_account_info(),
+                ),
+            false =>
+                (
+                    self.vault_y.to_account_info(),
+                    self.mint_y.to_account_info(),
+                    self.mint_y.decimals,
+                    self.token_program_y.to_account_info(),
+                ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            to: self.user_token.to_account_info(),
+            mint,
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[&b"config"[..], &self.config.seed.to_le_bytes(), &[self.config.config_bump]];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        transfer_checked(cpi_ctx, amount, decimals)
+    }
+
+    fn burn_lp_tokens(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = Burn {
+            mint: self.mint_lp.to_account_info(),
+            from: self.user_lp.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        burn(cpi_context, amount)
+    }
+}
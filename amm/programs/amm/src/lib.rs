@@ -1,25 +1,237 @@
 use anchor_lang::prelude::*;
+pub mod client;
+pub mod constants;
+pub mod curve;
+pub mod error;
+pub mod events;
 mod instructions;
-mod state;
+pub mod state;
+pub mod utils;
 declare_id!("3PqMs13NWeiZM8TU6mmSN6vkYqoWDPg1CHnHTa569ZM6");
 use crate::instructions::*;
+use crate::curve::CurveType;
+use crate::state::{ AllowlistMode, PriceBand, Twap };
+use crate::utils::check_expiry;
 #[program]
 pub mod amm {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>, fee: u16, seeds: u64) -> Result<()> {
-        ctx.accounts.initialize(fee, seeds, ctx.bumps)
+    pub fn create_registry(ctx: Context<CreateRegistry>) -> Result<()> {
+        ctx.accounts.create_registry(ctx.bumps)
     }
 
-    pub fn deposit(ctx: Context<Deposit>, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        fee: u16,
+        seeds: u64,
+        curve: CurveType
+    ) -> Result<()> {
+        ctx.accounts.initialize(fee, seeds, curve, ctx.bumps)
+    }
+
+    pub fn deposit(
+        ctx: Context<Deposit>,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        expires_at: Option<i64>
+    ) -> Result<()> {
+        check_expiry(expires_at)?;
         ctx.accounts.deposit(amount, max_x, max_y)
     }
 
-    pub fn swap(ctx: Context<Swap>, is_x: bool, amount_in: u64, min_out: u64) -> Result<()> {
+    pub fn deposit_single(
+        ctx: Context<DepositSingle>,
+        is_x: bool,
+        amount: u64,
+        min_lp: u64,
+        expires_at: Option<i64>
+    ) -> Result<()> {
+        check_expiry(expires_at)?;
+        ctx.accounts.deposit_single(is_x, amount, min_lp)
+    }
+
+    pub fn swap(
+        ctx: Context<Swap>,
+        is_x: bool,
+        amount_in: u64,
+        min_out: u64,
+        expires_at: Option<i64>
+    ) -> Result<()> {
+        check_expiry(expires_at)?;
         ctx.accounts.swap(is_x, amount_in, min_out)
     }
 
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
-        ctx.accounts.withdraw(amount, max_x, max_y)
+    pub fn swap_exact_out(
+        ctx: Context<Swap>,
+        is_x: bool,
+        amount_out: u64,
+        max_in: u64,
+        expires_at: Option<i64>
+    ) -> Result<()> {
+        check_expiry(expires_at)?;
+        ctx.accounts.swap_exact_out(is_x, amount_out, max_in)
+    }
+
+    pub fn swap_route<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SwapRoute<'info>>,
+        amount_in: u64,
+        min_out: u64,
+        expires_at: Option<i64>
+    ) -> Result<()> {
+        check_expiry(expires_at)?;
+        ctx.accounts.swap_route(ctx.remaining_accounts, amount_in, min_out)
+    }
+
+    pub fn withdraw(
+        ctx: Context<Withdraw>,
+        amount: u64,
+        min_x: u64,
+        min_y: u64,
+        expires_at: Option<i64>
+    ) -> Result<()> {
+        check_expiry(expires_at)?;
+        ctx.accounts.withdraw(amount, min_x, min_y)
+    }
+
+    pub fn withdraw_imbalanced(
+        ctx: Context<Withdraw>,
+        amount_x: u64,
+        amount_y: u64,
+        max_lp: u64,
+        expires_at: Option<i64>
+    ) -> Result<()> {
+        check_expiry(expires_at)?;
+        ctx.accounts.withdraw_imbalanced(amount_x, amount_y, max_lp)
+    }
+
+    pub fn withdraw_single(
+        ctx: Context<WithdrawSingle>,
+        is_x: bool,
+        amount: u64,
+        min_out: u64,
+        expires_at: Option<i64>
+    ) -> Result<()> {
+        check_expiry(expires_at)?;
+        ctx.accounts.withdraw_single(is_x, amount, min_out)
+    }
+
+    pub fn update_fee(ctx: Context<UpdateConfig>, fee: u16) -> Result<()> {
+        ctx.accounts.update_fee(fee)
+    }
+
+    pub fn enable_dynamic_fee(
+        ctx: Context<UpdateConfig>,
+        min_fee: u16,
+        max_fee: u16,
+        sensitivity: u16,
+        decay_period: u32
+    ) -> Result<()> {
+        ctx.accounts.enable_dynamic_fee(min_fee, max_fee, sensitivity, decay_period)
+    }
+
+    pub fn disable_dynamic_fee(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.disable_dynamic_fee()
+    }
+
+    pub fn update_protocol_fee(ctx: Context<UpdateConfig>, protocol_fee: u16) -> Result<()> {
+        ctx.accounts.update_protocol_fee(protocol_fee)
+    }
+
+    pub fn update_referral_fee(ctx: Context<UpdateConfig>, referral_fee: u16) -> Result<()> {
+        ctx.accounts.update_referral_fee(referral_fee)
+    }
+
+    pub fn set_allowlist(
+        ctx: Context<UpdateConfig>,
+        allowlist: Option<AllowlistMode>
+    ) -> Result<()> {
+        ctx.accounts.set_allowlist(allowlist)
+    }
+
+    pub fn set_price_band(ctx: Context<UpdateConfig>, price_band: Option<PriceBand>) -> Result<()> {
+        ctx.accounts.set_price_band(price_band)
+    }
+
+    pub fn lock(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.lock()
+    }
+
+    pub fn unlock(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.unlock()
+    }
+
+    pub fn transfer_authority(ctx: Context<UpdateConfig>, new_authority: Pubkey) -> Result<()> {
+        ctx.accounts.transfer_authority(new_authority)
+    }
+
+    pub fn renounce_authority(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.renounce_authority()
+    }
+
+    pub fn update_lp_metadata(
+        ctx: Context<UpdateLpMetadata>,
+        name: String,
+        symbol: String,
+        uri: String
+    ) -> Result<()> {
+        ctx.accounts.update_lp_metadata(name, symbol, uri)
+    }
+
+    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        ctx.accounts.collect_protocol_fees()
+    }
+
+    pub fn observe(ctx: Context<Observe>, seconds_ago: u32) -> Result<Twap> {
+        ctx.accounts.observe(seconds_ago)
+    }
+
+    pub fn set_observation_interval(ctx: Context<UpdateOracle>, interval: u32) -> Result<()> {
+        ctx.accounts.set_observation_interval(interval)
+    }
+
+    pub fn flash_borrow(ctx: Context<FlashBorrow>, is_x: bool, amount: u64) -> Result<()> {
+        ctx.accounts.flash_borrow(is_x, amount)
+    }
+
+    pub fn flash_repay(ctx: Context<FlashRepay>) -> Result<()> {
+        ctx.accounts.flash_repay()
+    }
+
+    pub fn create_farm(ctx: Context<CreateFarm>, reward_rate: u64) -> Result<()> {
+        ctx.accounts.create_farm(reward_rate, ctx.bumps)
+    }
+
+    pub fn fund_farm(ctx: Context<FundFarm>, amount: u64) -> Result<()> {
+        ctx.accounts.fund_farm(amount)
+    }
+
+    pub fn stake_lp(ctx: Context<StakeLp>, amount: u64) -> Result<()> {
+        ctx.accounts.stake_lp(amount, ctx.bumps)
+    }
+
+    pub fn unstake_lp(ctx: Context<StakeLp>, amount: u64) -> Result<()> {
+        ctx.accounts.unstake_lp(amount)
+    }
+
+    pub fn claim(ctx: Context<Claim>) -> Result<()> {
+        ctx.accounts.claim()
+    }
+
+    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
+        ctx.accounts.close_pool()
+    }
+
+    pub fn add_to_allowlist(
+        ctx: Context<AddToAllowlist>,
+        user: Pubkey,
+        can_deposit: bool,
+        can_swap: bool
+    ) -> Result<()> {
+        ctx.accounts.add_to_allowlist(user, can_deposit, can_swap, ctx.bumps)
+    }
+
+    pub fn remove_from_allowlist(ctx: Context<RemoveFromAllowlist>) -> Result<()> {
+        ctx.accounts.remove_from_allowlist()
     }
 }
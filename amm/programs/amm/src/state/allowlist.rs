@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+// per-user permissions on a permissioned pool, at seeds = [b"allowlist", config, user]
+#[account]
+#[derive(InitSpace)]
+pub struct AllowlistEntry {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub can_deposit: bool,
+    pub can_swap: bool,
+    pub bump: u8,
+}
@@ -1,4 +1,7 @@
 use anchor_lang::prelude::*;
+use constant_product_curve::{ SwapResult, XYAmounts };
+
+use crate::{ curve::{ mul_div, CurveType }, error::AmmError, state::AllowlistEntry };
 
 #[account]
 #[derive(InitSpace)]
@@ -6,9 +9,273 @@ pub struct Config {
     pub authority: Option<Pubkey>,
     pub seed: u64,
     pub fee: u16,
+    pub curve: CurveType,
+    // share of each swap fee kept by the protocol, in basis points of the fee
+    pub protocol_fee: u16,
+    // share of each swap fee paid to the referrer, when a swap names one
+    pub referral_fee: u16,
+    pub protocol_fees_x: u64,
+    pub protocol_fees_y: u64,
     pub mint_x: Pubkey,
     pub mint_y: Pubkey,
     pub locked: bool,
+    // outstanding flash loan, cleared by flash_repay in the same transaction
+    pub flash_loan: Option<FlashLoan>,
+    // when set, swaps pay a volatility-dependent fee instead of `fee`
+    pub dynamic_fee: Option<DynamicFee>,
+    // when set, the gated actions need an AllowlistEntry for the user; withdrawals stay open
+    pub allowlist: Option<AllowlistMode>,
+    // AllowlistEntry accounts open for this pool, close_pool waits for them to be removed
+    pub allowlist_entries: u32,
+    // set by create_farm, close_pool then needs the farm's accounts to close it as well
+    pub has_farm: bool,
+    // when set, swaps can't push the pool price outside a band around a reference price
+    pub price_band: Option<PriceBand>,
     pub config_bump: u8,
     pub lp_bump: u8,
 }
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct FlashLoan {
+    pub is_x: bool,
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, InitSpace)]
+pub struct PriceBand {
+    // any account whose data starts with the price of x quoted in y, as a little endian
+    // Q64.64 u128 like the oracle's prices
+    pub reference: Pubkey,
+    // in basis points of the reference price
+    pub max_deviation: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, InitSpace)]
+pub struct AllowlistMode {
+    pub deposit: bool,
+    pub swap: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, InitSpace)]
+pub struct DynamicFee {
+    pub min_fee: u16,
+    pub max_fee: u16,
+    // fee added per basis point of volatility, in ten-thousandths of a basis point
+    pub sensitivity: u16,
+    // seconds for the volatility accumulator to decay back to zero
+    pub decay_period: u32,
+    // recent reserve-ratio moves in basis points
+    pub volatility: u64,
+    pub last_update: i64,
+}
+
+impl DynamicFee {
+    // volatility left at `now`, decaying linearly since the last swap
+    pub fn volatility_at(&self, now: i64) -> u64 {
+        let elapsed = now.saturating_sub(self.last_update).max(0) as u64;
+        let period = self.decay_period as u64;
+        if elapsed >= period {
+            return 0;
+        }
+        (((self.volatility as u128) * ((period - elapsed) as u128)) / (period as u128)) as u64
+    }
+
+    pub fn fee(&self, now: i64) -> u16 {
+        let variable = ((self.volatility_at(now) as u128) * (self.sensitivity as u128)) / 10_000;
+        ((self.min_fee as u128) + variable).min(self.max_fee as u128) as u16
+    }
+
+    // adds the move of the y/x reserve ratio from (x0, y0) to (x1, y1)
+    pub fn record(&mut self, now: i64, x0: u64, y0: u64, x1: u64, y1: u64) {
+        self.volatility = self
+            .volatility_at(now)
+            .saturating_add(price_move_bps(x0, y0, x1, y1));
+        self.last_update = now;
+    }
+}
+
+// reserves once `res` has settled against (x, y)
+pub fn post_swap_reserves(x: u64, y: u64, is_x: bool, res: &SwapResult) -> (u64, u64) {
+    match is_x {
+        true => (x.saturating_add(res.deposit), y.saturating_sub(res.withdraw)),
+        false => (x.saturating_sub(res.withdraw), y.saturating_add(res.deposit)),
+    }
+}
+
+// relative change of the y/x reserve ratio from (x0, y0) to (x1, y1), in basis points
+pub fn price_move_bps(x0: u64, y0: u64, x1: u64, y1: u64) -> u64 {
+    let before = (y0 as u128) * (x1 as u128);
+    let after = (y1 as u128) * (x0 as u128);
+    match before {
+        0 => 0,
+        _ =>
+            mul_div(before.abs_diff(after), 10_000, before)
+                .map(|bps| bps.min(u64::MAX as u128) as u64)
+                .unwrap_or(u64::MAX),
+    }
+}
+
+impl Config {
+    // trading and liquidity changes are off while locked or while the vaults are lent out
+    pub fn check_tradable(&self) -> Result<()> {
+        require!(!self.locked, AmmError::PoolLocked);
+        require!(self.flash_loan.is_none(), AmmError::FlashLoanActive);
+        Ok(())
+    }
+
+    // `entry` is the caller's allowlist entry, if one was passed
+    pub fn check_depositor(&self, entry: Option<&AllowlistEntry>) -> Result<()> {
+        if self.allowlist.is_some_and(|mode| mode.deposit) {
+            require!(entry.is_some_and(|entry| entry.can_deposit), AmmError::NotAllowlisted);
+        }
+        Ok(())
+    }
+
+    pub fn check_trader(&self, entry: Option<&AllowlistEntry>) -> Result<()> {
+        if self.allowlist.is_some_and(|mode| mode.swap) {
+            require!(entry.is_some_and(|entry| entry.can_swap), AmmError::NotAllowlisted);
+        }
+        Ok(())
+    }
+
+    // `reference` has to be the band's reference account whenever a band is set
+    pub fn check_price_band(&self, reference: Option<&AccountInfo>, x: u64, y: u64) -> Result<()> {
+        let Some(band) = self.price_band else {
+            return Ok(());
+        };
+        let reference = reference.ok_or(AmmError::InvalidReferencePrice)?;
+        require_keys_eq!(reference.key(), band.reference, AmmError::InvalidReferencePrice);
+        let data = reference.try_borrow_data()?;
+        let price = data
+            .get(..16)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u128::from_le_bytes)
+            .filter(|price| *price > 0)
+            .ok_or(AmmError::InvalidReferencePrice)?;
+
+        require!(x > 0, AmmError::PriceOutOfBand);
+        let pool_price = ((y as u128) << 64) / (x as u128);
+        let deviation = mul_div(pool_price.abs_diff(price), 10_000, price).unwrap_or(u128::MAX);
+        require!(deviation <= (band.max_deviation as u128), AmmError::PriceOutOfBand);
+        Ok(())
+    }
+
+    // vault balances minus the accrued protocol fees, i.e. what belongs to LPs
+    // the vaults net of accrued protocol fees, a vault holding less than its fees means the
+    // accounting is off and nothing should be priced against it
+    pub fn reserves(&self, vault_x: u64, vault_y: u64) -> Result<(u64, u64)> {
+        Ok((
+            vault_x.checked_sub(self.protocol_fees_x).ok_or(AmmError::InsufficientReserves)?,
+            vault_y.checked_sub(self.protocol_fees_y).ok_or(AmmError::InsufficientReserves)?,
+        ))
+    }
+
+    pub fn accrue_protocol_fee(&mut self, is_x: bool, fee: u64) -> Result<()> {
+        let amount = ((fee as u128) * (self.protocol_fee as u128) / 10_000) as u64;
+        match is_x {
+            true => {
+                self.protocol_fees_x = self.protocol_fees_x
+                    .checked_add(amount)
+                    .ok_or(ProgramError::ArithmeticOverflow)?;
+            }
+            false => {
+                self.protocol_fees_y = self.protocol_fees_y
+                    .checked_add(amount)
+                    .ok_or(ProgramError::ArithmeticOverflow)?;
+            }
+        }
+        Ok(())
+    }
+
+    // fee charged at `now`, the static fee unless dynamic fees are on
+    pub fn current_fee(&self, now: i64) -> u16 {
+        match &self.dynamic_fee {
+            Some(dynamic_fee) => dynamic_fee.fee(now),
+            None => self.fee,
+        }
+    }
+
+    // feeds the price impact of a swap into the volatility accumulator
+    fn record_swap(&mut self, now: i64, x: u64, y: u64, is_x: bool, res: &SwapResult) {
+        let (x1, y1) = post_swap_reserves(x, y, is_x, res);
+        self.record_move(now, x, y, x1, y1);
+    }
+
+    // same for a reserve change priced outside the swap methods, e.g. an imbalanced withdrawal
+    pub fn record_move(&mut self, now: i64, x0: u64, y0: u64, x1: u64, y1: u64) {
+        if let Some(dynamic_fee) = self.dynamic_fee.as_mut() {
+            dynamic_fee.record(now, x0, y0, x1, y1);
+        }
+    }
+
+    pub fn referral_cut(&self, fee: u64) -> u64 {
+        ((fee as u128) * (self.referral_fee as u128) / 10_000) as u64
+    }
+
+    // prices a swap of `amount` (as received by the vault) against the LP reserves
+    // and books the protocol's cut of the fee
+    pub fn swap(&mut self, now: i64, x: u64, y: u64, is_x: bool, amount: u64) -> Result<SwapResult> {
+        let res = self.curve.swap(x, y, self.current_fee(now), is_x, amount)?;
+        self.accrue_protocol_fee(is_x, res.fee)?;
+        self.record_swap(now, x, y, is_x, &res);
+        Ok(res)
+    }
+
+    // same as swap, but for a fixed output amount
+    pub fn swap_exact_out(
+        &mut self,
+        now: i64,
+        x: u64,
+        y: u64,
+        is_x: bool,
+        amount_out: u64
+    ) -> Result<SwapResult> {
+        let res = self.curve.swap_exact_out(x, y, self.current_fee(now), is_x, amount_out)?;
+        self.accrue_protocol_fee(is_x, res.fee)?;
+        self.record_swap(now, x, y, is_x, &res);
+        Ok(res)
+    }
+
+    // single sided deposit, the internal swap pays the current fee and counts towards
+    // volatility like any other swap. Returns the LP to mint and the internal swap.
+    pub fn deposit_single(
+        &mut self,
+        now: i64,
+        x: u64,
+        y: u64,
+        supply: u64,
+        is_x: bool,
+        amount: u64
+    ) -> Result<(u64, SwapResult)> {
+        let (lp, res) = self.curve.deposit_single(x, y, self.current_fee(now), supply, is_x, amount)?;
+        self.accrue_protocol_fee(is_x, res.fee)?;
+        self.record_swap(now, x, y, is_x, &res);
+        Ok((lp, res))
+    }
+
+    // single sided withdrawal, booked the same way. Returns the payout and the internal swap,
+    // which runs against the reserves left after the balanced part of the withdrawal
+    pub fn withdraw_single(
+        &mut self,
+        now: i64,
+        x: u64,
+        y: u64,
+        supply: u64,
+        is_x: bool,
+        amount: u64
+    ) -> Result<(u64, SwapResult)> {
+        let (out, res) = self.curve.withdraw_single(x, y, self.current_fee(now), supply, is_x, amount)?;
+        self.accrue_protocol_fee(!is_x, res.fee)?;
+        let balanced = self.withdraw_amounts(x, y, supply, amount)?;
+        self.record_swap(now, x - balanced.x, y - balanced.y, !is_x, &res);
+        Ok((out, res))
+    }
+
+    pub fn deposit_amounts(&self, x: u64, y: u64, supply: u64, amount: u64) -> Result<XYAmounts> {
+        self.curve.deposit_amounts(x, y, supply, amount)
+    }
+
+    pub fn withdraw_amounts(&self, x: u64, y: u64, supply: u64, amount: u64) -> Result<XYAmounts> {
+        self.curve.withdraw_amounts(x, y, supply, amount)
+    }
+}
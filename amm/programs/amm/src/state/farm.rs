@@ -0,0 +1,183 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::REWARD_PRECISION;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Farm {
+    pub config: Pubkey,
+    pub reward_mint: Pubkey,
+    // reward tokens per second, shared by all staked LP
+    pub reward_rate: u64,
+    // rewards earned per staked LP token, scaled by REWARD_PRECISION
+    pub reward_per_share: u128,
+    pub last_update: i64,
+    pub total_staked: u64,
+    // funded rewards that have not been accrued to stakers yet
+    pub undistributed: u64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UserStake {
+    pub farm: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    // reward_per_share already accounted for on `amount`, scaled by REWARD_PRECISION
+    pub reward_debt: u128,
+    // settled but unclaimed rewards
+    pub pending: u64,
+    pub bump: u8,
+}
+
+impl Farm {
+    // accrues rewards up to `now`, capped by what has been funded
+    pub fn update(&mut self, now: i64) -> Result<()> {
+        let elapsed = now.saturating_sub(self.last_update) as u64;
+        if self.total_staked > 0 && elapsed > 0 {
+            let reward = self.reward_rate.saturating_mul(elapsed).min(self.undistributed);
+            self.reward_per_share = self.reward_per_share
+                .checked_add(((reward as u128) * REWARD_PRECISION) / (self.total_staked as u128))
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+            self.undistributed -= reward;
+        }
+        self.last_update = now;
+        Ok(())
+    }
+}
+
+impl UserStake {
+    // moves everything earned since the last settle into `pending`, call after Farm::update
+    // and again via reset_debt once `amount` changed
+    pub fn settle(&mut self, farm: &Farm) -> Result<()> {
+        let earned = (self.amount as u128).saturating_mul(farm.reward_per_share);
+        let owed = (earned.saturating_sub(self.reward_debt) / REWARD_PRECISION) as u64;
+        self.pending = self.pending.checked_add(owed).ok_or(ProgramError::ArithmeticOverflow)?;
+        self.reward_debt = earned;
+        Ok(())
+    }
+
+    pub fn reset_debt(&mut self, farm: &Farm) {
+        self.reward_debt = (self.amount as u128).saturating_mul(farm.reward_per_share);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn farm(reward_rate: u64) -> Farm {
+        Farm {
+            config: Pubkey::default(),
+            reward_mint: Pubkey::default(),
+            reward_rate,
+            reward_per_share: 0,
+            last_update: 0,
+            total_staked: 0,
+            undistributed: 0,
+            bump: 0,
+        }
+    }
+
+    fn stake() -> UserStake {
+        UserStake {
+            farm: Pubkey::default(),
+            owner: Pubkey::default(),
+            amount: 0,
+            reward_debt: 0,
+            pending: 0,
+            bump: 0,
+        }
+    }
+
+    // what stake_lp and unstake_lp do to the accounting, `amount` < 0 unstakes
+    fn restake(farm: &mut Farm, user: &mut UserStake, now: i64, amount: i64) {
+        farm.update(now).unwrap();
+        user.settle(farm).unwrap();
+        user.amount = user.amount.checked_add_signed(amount).unwrap();
+        farm.total_staked = farm.total_staked.checked_add_signed(amount).unwrap();
+        user.reset_debt(farm);
+    }
+
+    fn claim(farm: &mut Farm, user: &mut UserStake, now: i64) -> u64 {
+        farm.update(now).unwrap();
+        user.settle(farm).unwrap();
+        std::mem::take(&mut user.pending)
+    }
+
+    #[test]
+    fn rewards_follow_the_stake_over_time() {
+        let mut farm = farm(100);
+        farm.undistributed = 1_000_000;
+        let (mut alice, mut bob) = (stake(), stake());
+
+        // alice alone for 10s, then both with bob holding 3x her stake for 10s
+        restake(&mut farm, &mut alice, 0, 1_000);
+        restake(&mut farm, &mut bob, 10, 3_000);
+        // bob leaves, alice keeps the whole rate for another 10s
+        restake(&mut farm, &mut bob, 20, -3_000);
+
+        assert_eq!(claim(&mut farm, &mut alice, 30), 1_000 + 250 + 1_000);
+        assert_eq!(claim(&mut farm, &mut bob, 30), 750);
+        assert_eq!(farm.undistributed, 1_000_000 - 3_000);
+        // nothing left to claim twice
+        assert_eq!(claim(&mut farm, &mut alice, 30), 0);
+    }
+
+    #[test]
+    fn funding_without_stakers_waits_for_them() {
+        let mut farm = farm(100);
+        let mut alice = stake();
+        farm.update(0).unwrap();
+        farm.undistributed = 5_000;
+
+        // no one to pay while nothing is staked, the rewards stay undistributed
+        farm.update(1_000).unwrap();
+        assert_eq!(farm.reward_per_share, 0);
+        assert_eq!(farm.undistributed, 5_000);
+
+        restake(&mut farm, &mut alice, 1_000, 10);
+        assert_eq!(claim(&mut farm, &mut alice, 1_010), 1_000);
+        assert_eq!(farm.undistributed, 4_000);
+    }
+
+    #[test]
+    fn rounding_dust_stays_in_the_farm() {
+        let mut farm = farm(1);
+        farm.undistributed = 1_000;
+        let mut users = [stake(), stake(), stake()];
+        for user in users.iter_mut() {
+            restake(&mut farm, user, 0, 1);
+        }
+
+        // a third of a token per second each, which never divides evenly
+        let claimed: u64 = users
+            .iter_mut()
+            .map(|user| claim(&mut farm, user, 100))
+            .sum();
+        assert_eq!(farm.undistributed, 900);
+        assert!((99..=100).contains(&claimed), "claimed {}", claimed);
+    }
+
+    #[test]
+    fn rewards_stop_once_funding_runs_out() {
+        let mut farm = farm(100);
+        farm.undistributed = 1_500;
+        let mut alice = stake();
+        restake(&mut farm, &mut alice, 0, 7);
+
+        // the per share reward rounds down, which costs a stake of 7 a token here
+        assert_eq!(claim(&mut farm, &mut alice, 10), 999);
+        // funded for 15s, claims well after that get what was funded and nothing more
+        let rest = claim(&mut farm, &mut alice, 1_000);
+        assert_eq!(farm.undistributed, 0);
+        assert!((499..=500).contains(&rest));
+        assert_eq!(claim(&mut farm, &mut alice, 2_000), 0);
+
+        // refunding picks up from the refund, not from when the funds ran out
+        farm.update(2_000).unwrap();
+        farm.undistributed = 100;
+        assert!(claim(&mut farm, &mut alice, 2_001) >= 99);
+    }
+}
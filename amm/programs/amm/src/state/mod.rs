@@ -1,3 +1,11 @@
+mod allowlist;
 mod config;
+mod farm;
+mod oracle;
+mod registry;
 
+pub use allowlist::*;
 pub use config::*;
+pub use farm::*;
+pub use oracle::*;
+pub use registry::*;
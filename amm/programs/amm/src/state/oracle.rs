@@ -0,0 +1,197 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+
+pub const OBSERVATION_COUNT: usize = 32;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct Observation {
+    pub timestamp: i64,
+    // running sums of price * seconds in Q64.64, allowed to wrap
+    pub price_x_cumulative: u128,
+    pub price_y_cumulative: u128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct Twap {
+    // time weighted prices in Q64.64, price_x is x quoted in y
+    pub price_x: u128,
+    pub price_y: u128,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Oracle {
+    pub config: Pubkey,
+    // slot of the most recent observation
+    pub index: u16,
+    // seconds an observation has to cover before the next one gets its own slot, so the
+    // history reaches back at least OBSERVATION_COUNT intervals however busy the pool is
+    pub interval: u32,
+    pub bump: u8,
+    pub observations: [Observation; OBSERVATION_COUNT],
+}
+
+impl Oracle {
+    pub fn init(config: Pubkey, now: i64, interval: u32, bump: u8) -> Self {
+        let mut observations = [Observation::default(); OBSERVATION_COUNT];
+        observations[0].timestamp = now;
+        Oracle { config, index: 0, interval, bump, observations }
+    }
+
+    // records the price the pool held since the last observation, call before reserves change.
+    // The newest observation is moved forward in place until it is `interval` past the one
+    // before it, the cumulative sums stay exact either way
+    pub fn update(&mut self, now: i64, x: u64, y: u64) {
+        let last = self.observations[self.index as usize];
+        if now <= last.timestamp {
+            return;
+        }
+        let prev = self.observations[(self.index as usize + OBSERVATION_COUNT - 1) % OBSERVATION_COUNT];
+        if prev.timestamp == 0 || last.timestamp - prev.timestamp >= (self.interval as i64) {
+            self.index = ((self.index as usize + 1) % OBSERVATION_COUNT) as u16;
+        }
+        self.observations[self.index as usize] = Self::extend(&last, now, x, y);
+    }
+
+    // cumulative prices as of `seconds_ago` before `now`, given the current reserves
+    pub fn observe(&self, now: i64, x: u64, y: u64, seconds_ago: u32) -> Result<Observation> {
+        let target = now - (seconds_ago as i64);
+        let last = self.observations[self.index as usize];
+        if target >= last.timestamp {
+            return Ok(Self::extend(&last, target, x, y));
+        }
+
+        // walk back from the newest observation until one is at or before the target
+        let mut next = last;
+        for i in 1..OBSERVATION_COUNT {
+            let slot = (self.index as usize + OBSERVATION_COUNT - i) % OBSERVATION_COUNT;
+            let prev = self.observations[slot];
+            if prev.timestamp == 0 {
+                break;
+            }
+            if prev.timestamp <= target {
+                let elapsed = (target - prev.timestamp) as u128;
+                let span = (next.timestamp - prev.timestamp) as u128;
+                return Ok(Observation {
+                    timestamp: target,
+                    price_x_cumulative: prev.price_x_cumulative.wrapping_add(
+                        (next.price_x_cumulative.wrapping_sub(prev.price_x_cumulative) / span) *
+                            elapsed
+                    ),
+                    price_y_cumulative: prev.price_y_cumulative.wrapping_add(
+                        (next.price_y_cumulative.wrapping_sub(prev.price_y_cumulative) / span) *
+                            elapsed
+                    ),
+                });
+            }
+            next = prev;
+        }
+        err!(AmmError::ObservationTooOld)
+    }
+
+    pub fn twap(&self, now: i64, x: u64, y: u64, seconds_ago: u32) -> Result<Twap> {
+        require!(seconds_ago > 0, AmmError::ObservationTooOld);
+        let start = self.observe(now, x, y, seconds_ago)?;
+        let end = self.observe(now, x, y, 0)?;
+        let span = seconds_ago as u128;
+        Ok(Twap {
+            price_x: end.price_x_cumulative.wrapping_sub(start.price_x_cumulative) / span,
+            price_y: end.price_y_cumulative.wrapping_sub(start.price_y_cumulative) / span,
+        })
+    }
+
+    fn extend(last: &Observation, now: i64, x: u64, y: u64) -> Observation {
+        let elapsed = (now - last.timestamp) as u128;
+        let (price_x, price_y) = match x == 0 || y == 0 {
+            true => (0, 0),
+            false => (((y as u128) << 64) / (x as u128), ((x as u128) << 64) / (y as u128)),
+        };
+        Observation {
+            timestamp: now,
+            price_x_cumulative: last.price_x_cumulative.wrapping_add(
+                price_x.wrapping_mul(elapsed)
+            ),
+            price_y_cumulative: last.price_y_cumulative.wrapping_add(
+                price_y.wrapping_mul(elapsed)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1 << 64;
+
+    fn assert_too_old(res: Result<Observation>) {
+        match res {
+            Err(Error::AnchorError(err)) => {
+                assert_eq!(err.error_code_number, u32::from(AmmError::ObservationTooOld));
+            }
+            _ => panic!("expected ObservationTooOld"),
+        }
+    }
+
+    #[test]
+    fn observe_interpolates_between_observations() {
+        let mut oracle = Oracle::init(Pubkey::default(), 1_000, 1, 0);
+        // price 1 for 100s, then 4 for 100s, then 2 up to now
+        oracle.update(1_100, 1_000, 1_000);
+        oracle.update(1_200, 1_000, 4_000);
+        let now = 1_300;
+        let (x, y) = (1_000, 2_000);
+
+        let at = |seconds_ago| oracle.observe(now, x, y, seconds_ago).unwrap().price_x_cumulative;
+        assert_eq!(at(300), 0);
+        assert_eq!(at(250), 50 * ONE);
+        assert_eq!(at(200), 100 * ONE);
+        assert_eq!(at(150), 300 * ONE);
+        assert_eq!(at(100), 500 * ONE);
+        // past the newest observation the current reserves carry the sum forward
+        assert_eq!(at(0), 700 * ONE);
+
+        assert_eq!(oracle.twap(now, x, y, 300).unwrap().price_x, (700 * ONE) / 300);
+        assert_eq!(oracle.twap(now, x, y, 150).unwrap().price_x, (400 * ONE) / 150);
+        // y quoted in x is the same walk with the reciprocal prices
+        assert_eq!(oracle.twap(now, x, y, 100).unwrap().price_y, ONE / 2);
+    }
+
+    #[test]
+    fn observe_rejects_targets_before_the_history() {
+        let mut oracle = Oracle::init(Pubkey::default(), 1_000, 1, 0);
+        oracle.update(1_010, 1, 1);
+        assert_too_old(oracle.observe(1_020, 1, 1, 21));
+        assert!(oracle.observe(1_020, 1, 1, 20).is_ok());
+        assert!(oracle.twap(1_020, 1, 1, 0).is_err());
+
+        // once the buffer has wrapped the oldest observations are gone
+        for now in 1_011..1_100 {
+            oracle.update(now, 1, 1);
+        }
+        let oldest = 1_099 - (OBSERVATION_COUNT as i64) + 1;
+        assert!(oracle.observe(1_099, 1, 1, (1_099 - oldest) as u32).is_ok());
+        assert_too_old(oracle.observe(1_099, 1, 1, (1_099 - oldest + 1) as u32));
+    }
+
+    #[test]
+    fn busy_pools_keep_history_for_the_whole_interval() {
+        let start = 1_000_000;
+        let mut oracle = Oracle::init(Pubkey::default(), start, 60, 0);
+        // a trade every second for two hours, at a price that keeps moving
+        for t in 1..7_200 {
+            oracle.update(start + t, 1_000, 1_000 + (t as u64 % 7));
+        }
+        let now = start + 7_200;
+        let span = (OBSERVATION_COUNT as u32 - 1) * 60;
+        assert!(oracle.observe(now, 1_000, 1_000, span).is_ok());
+
+        // every second was still counted at the price it traded at, the reserves passed to
+        // update(t) are the ones that held up to t
+        let price = |y: u64| ((y as u128) << 64) / 1_000;
+        let exact = (1..7_200).map(|t| price(1_000 + (t as u64 % 7))).sum::<u128>() + price(1_000);
+        let walked = oracle.observe(now, 1_000, 1_000, 0).unwrap().price_x_cumulative;
+        assert_eq!(walked, exact);
+    }
+}
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Registry {
+    pub pool_count: u64,
+    pub bump: u8,
+}
+
+// one per (mint_x, mint_y, creation fee tier) with mint_x < mint_y, found at
+// seeds = [b"pool", mint_x, mint_y, fee]. The tier is the fee the pool was initialized with
+// and stays its key when update_fee changes what swaps pay, so look pools up by the fee they
+// were created at, or enumerate them through PoolIndex
+#[account]
+#[derive(InitSpace)]
+pub struct PoolEntry {
+    pub config: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    // creation fee tier, update_fee does not move it
+    pub fee: u16,
+    // position in registration order, 0..registry.pool_count
+    pub index: u64,
+    pub bump: u8,
+}
+
+// one per registered pool, found at seeds = [b"pool", index] for index in
+// 0..registry.pool_count, so pools can be listed without scanning program accounts.
+// close_pool closes it, leaving a gap at that index
+#[account]
+#[derive(InitSpace)]
+pub struct PoolIndex {
+    pub config: Pubkey,
+    pub pool_entry: Pubkey,
+    pub bump: u8,
+}
@@ -0,0 +1,98 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    metadata::{ mpl_token_metadata::MAX_SYMBOL_LENGTH, MetadataAccount },
+    token::Token,
+    token_2022::spl_token_2022::{
+        extension::{ transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions },
+        state::Mint as MintState,
+    },
+    token_interface::Mint,
+};
+
+use crate::error::AmmError;
+
+// fee withheld by the transfer-fee extension when `amount` is sent, zero for legacy mints
+pub fn transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let info = mint.to_account_info();
+    if *info.owner == Token::id() {
+        return Ok(0);
+    }
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)?;
+    match state.get_extension::<TransferFeeConfig>() {
+        Ok(config) =>
+            config
+                .calculate_epoch_fee(Clock::get()?.epoch, amount)
+                .ok_or(ProgramError::ArithmeticOverflow.into()),
+        Err(_) => Ok(0),
+    }
+}
+
+// what actually arrives when `amount` is sent
+pub fn amount_received(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    Ok(amount - transfer_fee(mint, amount)?)
+}
+
+// what has to be sent so that `amount` arrives
+pub fn amount_to_send(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let info = mint.to_account_info();
+    if *info.owner == Token::id() {
+        return Ok(amount);
+    }
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)?;
+    match state.get_extension::<TransferFeeConfig>() {
+        Ok(config) =>
+            config
+                .get_epoch_fee(Clock::get()?.epoch)
+                .calculate_pre_fee_amount(amount)
+                .ok_or(ProgramError::ArithmeticOverflow.into()),
+        Err(_) => Ok(amount),
+    }
+}
+
+// trading instructions take an optional unix timestamp after which they must not execute
+pub fn check_expiry(expires_at: Option<i64>) -> Result<()> {
+    if let Some(expires_at) = expires_at {
+        require!(Clock::get()?.unix_timestamp <= expires_at, AmmError::Expired);
+    }
+    Ok(())
+}
+
+// floor of the square root
+pub fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = n.div_ceil(2);
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+// symbol from a Token Metadata account if there is one, else a short prefix of the mint
+pub fn token_symbol(metadata: Option<&UncheckedAccount>, mint: &Pubkey) -> String {
+    let symbol = metadata
+        .filter(|metadata| !metadata.data_is_empty())
+        .and_then(|metadata| {
+            MetadataAccount::try_deserialize(&mut &metadata.try_borrow_data().ok()?[..]).ok()
+        })
+        .map(|metadata| metadata.symbol.trim_matches(char::from(0)).trim().to_string())
+        .filter(|symbol| !symbol.is_empty());
+    match symbol {
+        Some(symbol) => symbol,
+        None => mint.to_string()[..4].to_string(),
+    }
+}
+
+// "X-Y" truncated to what Token Metadata accepts
+pub fn lp_symbol(symbol_x: &str, symbol_y: &str) -> String {
+    let mut symbol = format!("{}-{}", symbol_x, symbol_y);
+    while symbol.len() > MAX_SYMBOL_LENGTH {
+        symbol.pop();
+    }
+    symbol
+}
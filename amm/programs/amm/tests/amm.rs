@@ -0,0 +1,1594 @@
+// End to end tests against the program running in-process on solana-program-test, with the
+// SPL programs it ships. The metadata accounts of `initialize` are left out, so no Metaplex
+// binary is needed and nothing touches the network.
+
+use amm::{
+    client::{
+        allowlist_entry_address,
+        farm_address,
+        pool_entry_address,
+        pool_index_address,
+        registry_address,
+        user_stake_address,
+        PoolAddresses,
+    },
+    constants::MINIMUM_LIQUIDITY,
+    curve::{ CurveType, MAX_AMP },
+    error::AmmError,
+    state::{ AllowlistMode, Config, Farm, PoolEntry, PoolIndex, PriceBand, Registry, UserStake },
+};
+use anchor_lang::{
+    solana_program::{
+        account_info::AccountInfo,
+        clock::Clock,
+        entrypoint::ProgramResult,
+        pubkey::Pubkey,
+        system_instruction,
+        system_program,
+        sysvar,
+    },
+    AccountDeserialize,
+    InstructionData,
+    ToAccountMetas,
+};
+use anchor_spl::{
+    associated_token::{
+        get_associated_token_address_with_program_id,
+        spl_associated_token_account,
+    },
+    token::spl_token,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{
+            transfer_fee::instruction::initialize_transfer_fee_config,
+            ExtensionType,
+            StateWithExtensions,
+        },
+    },
+};
+use proptest::prelude::*;
+use solana_program_test::{ processor, BanksClientError, ProgramTest, ProgramTestContext };
+use solana_sdk::{
+    account::Account,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{ AccountMeta, Instruction, InstructionError },
+    signature::{ Keypair, Signer },
+    transaction::{ Transaction, TransactionError },
+};
+
+const SEED: u64 = 42;
+const FEE: u16 = 30;
+const DECIMALS: u8 = 6;
+// minted to the user on each side
+const BALANCE: u64 = 1_000_000_000_000_000;
+
+// anchor's entry wants the account slice to live as long as the accounts themselves
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    amm::entry(program_id, accounts, data)
+}
+
+struct Pool {
+    ctx: ProgramTestContext,
+    // makes otherwise identical transactions distinct
+    nonce: u64,
+    mint_x: Pubkey,
+    mint_y: Pubkey,
+    mint_lp: Pubkey,
+    token_program_x: Pubkey,
+    token_program_y: Pubkey,
+    config: Pubkey,
+    oracle: Pubkey,
+    vault_x: Pubkey,
+    vault_y: Pubkey,
+    locked_lp: Pubkey,
+    user_x: Pubkey,
+    user_y: Pubkey,
+    user_lp: Pubkey,
+    // passed to deposit and swap once the user is on the allowlist
+    allowlist_entry: Option<Pubkey>,
+    // passed to swap, a token account of the input mint
+    referrer: Option<Pubkey>,
+    // passed to swap once a price band is set
+    reference_price: Option<Pubkey>,
+}
+
+impl Pool {
+    // a funded user (the payer) and an initialized, empty pool
+    async fn new() -> Self {
+        Self::with_curve(CurveType::ConstantProduct).await
+    }
+
+    async fn with_curve(curve: CurveType) -> Self {
+        Self::with_transfer_fees(curve, [None, None]).await
+    }
+
+    // a side with a transfer fee, in basis points, gets a Token-2022 mint charging it
+    async fn with_transfer_fees(curve: CurveType, transfer_fees: [Option<u16>; 2]) -> Self {
+        let program_test = ProgramTest::new("amm", amm::ID, processor!(process_instruction));
+        let ctx = program_test.start_with_context().await;
+        let user = ctx.payer.pubkey();
+
+        let mut mints = [Keypair::new(), Keypair::new()];
+        mints.sort_by_key(|mint| mint.pubkey());
+        let (mint_x, mint_y) = (mints[0].pubkey(), mints[1].pubkey());
+        let [token_program_x, token_program_y] = transfer_fees.map(|fee| match fee {
+            Some(_) => spl_token_2022::ID,
+            None => spl_token::ID,
+        });
+
+        let addresses = PoolAddresses::new(
+            SEED,
+            &mint_x,
+            &mint_y,
+            &token_program_x,
+            &token_program_y,
+            &spl_token::ID
+        );
+        let (config, mint_lp) = (addresses.config, addresses.mint_lp);
+        let ata = |wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey| {
+            get_associated_token_address_with_program_id(wallet, mint, token_program)
+        };
+
+        let mut pool = Pool {
+            ctx,
+            nonce: 0,
+            mint_x,
+            mint_y,
+            mint_lp,
+            token_program_x,
+            token_program_y,
+            config,
+            oracle: addresses.oracle,
+            vault_x: addresses.vault_x,
+            vault_y: addresses.vault_y,
+            locked_lp: addresses.locked_lp,
+            user_x: ata(&user, &mint_x, &token_program_x),
+            user_y: ata(&user, &mint_y, &token_program_y),
+            user_lp: ata(&user, &mint_lp, &spl_token::ID),
+            allowlist_entry: None,
+            referrer: None,
+            reference_price: None,
+        };
+
+        let rent = pool.ctx.banks_client.get_rent().await.unwrap();
+        let mut ixs = Vec::new();
+        let sides = [
+            (&mints[0], pool.user_x, token_program_x, transfer_fees[0]),
+            (&mints[1], pool.user_y, token_program_y, transfer_fees[1]),
+        ];
+        for (mint, user_token, token_program, transfer_fee) in sides {
+            let extensions = match transfer_fee {
+                Some(_) => vec![ExtensionType::TransferFeeConfig],
+                None => vec![],
+            };
+            let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(
+                &extensions
+            ).unwrap();
+            ixs.push(
+                system_instruction::create_account(
+                    &user,
+                    &mint.pubkey(),
+                    rent.minimum_balance(space),
+                    space as u64,
+                    &token_program
+                )
+            );
+            // extensions are set up before the mint itself, uncapped so the fee is always the rate
+            if let Some(basis_points) = transfer_fee {
+                ixs.push(
+                    initialize_transfer_fee_config(
+                        &token_program,
+                        &mint.pubkey(),
+                        Some(&user),
+                        Some(&user),
+                        basis_points,
+                        u64::MAX
+                    ).unwrap()
+                );
+            }
+            // the 2022 program builds these for either token program
+            ixs.extend([
+                spl_token_2022::instruction
+                    ::initialize_mint2(&token_program, &mint.pubkey(), &user, None, DECIMALS)
+                    .unwrap(),
+                spl_associated_token_account::instruction::create_associated_token_account(
+                    &user,
+                    &user,
+                    &mint.pubkey(),
+                    &token_program
+                ),
+                spl_token_2022::instruction
+                    ::mint_to(&token_program, &mint.pubkey(), &user_token, &user, &[], BALANCE)
+                    .unwrap(),
+            ]);
+        }
+        pool.send_signed(&ixs, &[&mints[0], &mints[1]]).await.unwrap();
+
+        let registry = registry_address().0;
+        let create_registry = pool.ix(
+            amm::accounts::CreateRegistry {
+                payer: user,
+                registry,
+                system_program: system_program::ID,
+            },
+            amm::instruction::CreateRegistry {}
+        );
+        let initialize = pool.ix(
+            amm::accounts::Initialize {
+                initializer: user,
+                mint_x,
+                mint_y,
+                mint_lp,
+                vault_x: pool.vault_x,
+                vault_y: pool.vault_y,
+                locked_lp: pool.locked_lp,
+                config,
+                oracle: pool.oracle,
+                registry,
+                pool_entry: pool_entry_address(&mint_x, &mint_y, FEE).0,
+                pool_index: pool_index_address(0).0,
+                lp_metadata: None,
+                metadata_x: None,
+                metadata_y: None,
+                metadata_program: None,
+                rent: sysvar::rent::ID,
+                system_program: system_program::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                token_program: spl_token::ID,
+                token_program_x,
+                token_program_y,
+            },
+            amm::instruction::Initialize { fee: FEE, seeds: SEED, curve }
+        );
+        pool.send(&[create_registry, initialize]).await.unwrap();
+        pool
+    }
+
+    fn ix(&self, accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+        Instruction {
+            program_id: amm::ID,
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+
+    async fn send(&mut self, ixs: &[Instruction]) -> Result<(), BanksClientError> {
+        self.send_signed(ixs, &[]).await
+    }
+
+    async fn send_signed(
+        &mut self,
+        ixs: &[Instruction],
+        signers: &[&Keypair]
+    ) -> Result<(), BanksClientError> {
+        self.nonce += 1;
+        let mut all = vec![ComputeBudgetInstruction::set_compute_unit_price(self.nonce)];
+        all.extend_from_slice(ixs);
+        let mut keypairs = vec![&self.ctx.payer];
+        keypairs.extend_from_slice(signers);
+        let blockhash = self.ctx.banks_client.get_latest_blockhash().await?;
+        let tx = Transaction::new_signed_with_payer(
+            &all,
+            Some(&self.ctx.payer.pubkey()),
+            &keypairs,
+            blockhash
+        );
+        self.ctx.banks_client.process_transaction(tx).await
+    }
+
+    async fn deposit(&mut self, amount: u64, max_x: u64, max_y: u64) -> Result<(), BanksClientError> {
+        let ix = self.ix(
+            amm::accounts::Deposit {
+                user: self.ctx.payer.pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                mint_lp: self.mint_lp,
+                user_x: self.user_x,
+                user_y: self.user_y,
+                user_lp: self.user_lp,
+                locked_lp: self.locked_lp,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                config: self.config,
+                oracle: self.oracle,
+                allowlist_entry: self.allowlist_entry,
+                system_program: system_program::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                token_program: spl_token::ID,
+                token_program_x: self.token_program_x,
+                token_program_y: self.token_program_y,
+            },
+            amm::instruction::Deposit { amount, max_x, max_y, expires_at: None }
+        );
+        self.send(&[ix]).await
+    }
+
+    async fn swap(&mut self, is_x: bool, amount_in: u64, min_out: u64) -> Result<(), BanksClientError> {
+        self.swap_until(is_x, amount_in, min_out, None).await
+    }
+
+    async fn swap_until(
+        &mut self,
+        is_x: bool,
+        amount_in: u64,
+        min_out: u64,
+        expires_at: Option<i64>
+    ) -> Result<(), BanksClientError> {
+        let ix = self.ix(
+            self.swap_accounts(),
+            amm::instruction::Swap { is_x, amount_in, min_out, expires_at }
+        );
+        self.send(&[ix]).await
+    }
+
+    async fn swap_exact_out(
+        &mut self,
+        is_x: bool,
+        amount_out: u64,
+        max_in: u64
+    ) -> Result<(), BanksClientError> {
+        let ix = self.ix(
+            self.swap_accounts(),
+            amm::instruction::SwapExactOut { is_x, amount_out, max_in, expires_at: None }
+        );
+        self.send(&[ix]).await
+    }
+
+    fn swap_accounts(&self) -> amm::accounts::Swap {
+        amm::accounts::Swap {
+            user: self.ctx.payer.pubkey(),
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            user_x: self.user_x,
+            user_y: self.user_y,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            config: self.config,
+            oracle: self.oracle,
+            allowlist_entry: self.allowlist_entry,
+            referrer: self.referrer,
+            reference_price: self.reference_price,
+            system_program: system_program::ID,
+            associated_token_program: spl_associated_token_account::ID,
+            token_program_x: self.token_program_x,
+            token_program_y: self.token_program_y,
+        }
+    }
+
+    // a route of `hops` swaps through this pool, starting from x when `is_x`
+    async fn swap_route(
+        &mut self,
+        is_x: bool,
+        hops: usize,
+        amount_in: u64,
+        min_out: u64
+    ) -> Result<(), BanksClientError> {
+        let (mint_in, user_in) = match is_x {
+            true => (self.mint_x, self.user_x),
+            false => (self.mint_y, self.user_y),
+        };
+        let (mint_out, user_out) = match is_x == hops.is_multiple_of(2) {
+            true => (self.mint_x, self.user_x),
+            false => (self.mint_y, self.user_y),
+        };
+        let mut ix = self.ix(
+            amm::accounts::SwapRoute {
+                user: self.ctx.payer.pubkey(),
+                mint_in,
+                mint_out,
+                user_in,
+                user_out,
+                system_program: system_program::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                token_program_in: self.token_program(&mint_in),
+                token_program_out: self.token_program(&mint_out),
+            },
+            amm::instruction::SwapRoute { amount_in, min_out, expires_at: None }
+        );
+        for _ in 0..hops {
+            ix.accounts.extend([
+                AccountMeta::new(self.config, false),
+                AccountMeta::new_readonly(self.mint_x, false),
+                AccountMeta::new_readonly(self.mint_y, false),
+                AccountMeta::new(self.vault_x, false),
+                AccountMeta::new(self.vault_y, false),
+                AccountMeta::new_readonly(self.token_program_x, false),
+                AccountMeta::new_readonly(self.token_program_y, false),
+                AccountMeta::new(self.oracle, false),
+            ]);
+        }
+        self.send(&[ix]).await
+    }
+
+    async fn withdraw(&mut self, amount: u64, min_x: u64, min_y: u64) -> Result<(), BanksClientError> {
+        let ix = self.ix(
+            amm::accounts::Withdraw {
+                user: self.ctx.payer.pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                oracle: self.oracle,
+                mint_lp: self.mint_lp,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                user_x: self.user_x,
+                user_y: self.user_y,
+                user_lp: self.user_lp,
+                allowlist_entry: self.allowlist_entry,
+                reference_price: self.reference_price,
+                token_program: spl_token::ID,
+                token_program_x: self.token_program_x,
+                token_program_y: self.token_program_y,
+                system_program: system_program::ID,
+                associated_token_program: spl_associated_token_account::ID,
+            },
+            amm::instruction::Withdraw { amount, min_x, min_y, expires_at: None }
+        );
+        self.send(&[ix]).await
+    }
+
+    async fn withdraw_imbalanced(
+        &mut self,
+        amount_x: u64,
+        amount_y: u64,
+        max_lp: u64
+    ) -> Result<(), BanksClientError> {
+        let ix = self.ix(
+            amm::accounts::Withdraw {
+                user: self.ctx.payer.pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                oracle: self.oracle,
+                mint_lp: self.mint_lp,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                user_x: self.user_x,
+                user_y: self.user_y,
+                user_lp: self.user_lp,
+                allowlist_entry: self.allowlist_entry,
+                reference_price: self.reference_price,
+                token_program: spl_token::ID,
+                token_program_x: self.token_program_x,
+                token_program_y: self.token_program_y,
+                system_program: system_program::ID,
+                associated_token_program: spl_associated_token_account::ID,
+            },
+            amm::instruction::WithdrawImbalanced { amount_x, amount_y, max_lp, expires_at: None }
+        );
+        self.send(&[ix]).await
+    }
+
+    async fn deposit_single(&mut self, is_x: bool, amount: u64, min_lp: u64) -> Result<(), BanksClientError> {
+        let ix = self.deposit_single_ix(is_x, amount, min_lp);
+        self.send(&[ix]).await
+    }
+
+    fn deposit_single_ix(&self, is_x: bool, amount: u64, min_lp: u64) -> Instruction {
+        self.ix(
+            amm::accounts::DepositSingle {
+                user: self.ctx.payer.pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                mint_lp: self.mint_lp,
+                user_token: match is_x {
+                    true => self.user_x,
+                    false => self.user_y,
+                },
+                user_lp: self.user_lp,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                config: self.config,
+                oracle: self.oracle,
+                allowlist_entry: self.allowlist_entry,
+                reference_price: self.reference_price,
+                system_program: system_program::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                token_program: spl_token::ID,
+                token_program_x: self.token_program_x,
+                token_program_y: self.token_program_y,
+            },
+            amm::instruction::DepositSingle { is_x, amount, min_lp, expires_at: None }
+        )
+    }
+
+    async fn withdraw_single(&mut self, is_x: bool, amount: u64, min_out: u64) -> Result<(), BanksClientError> {
+        let ix = self.ix(
+            amm::accounts::WithdrawSingle {
+                user: self.ctx.payer.pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                oracle: self.oracle,
+                mint_lp: self.mint_lp,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                user_token: match is_x {
+                    true => self.user_x,
+                    false => self.user_y,
+                },
+                user_lp: self.user_lp,
+                allowlist_entry: self.allowlist_entry,
+                reference_price: self.reference_price,
+                token_program: spl_token::ID,
+                token_program_x: self.token_program_x,
+                token_program_y: self.token_program_y,
+                system_program: system_program::ID,
+                associated_token_program: spl_associated_token_account::ID,
+            },
+            amm::instruction::WithdrawSingle { is_x, amount, min_out, expires_at: None }
+        );
+        self.send(&[ix]).await
+    }
+
+    async fn close_pool(&mut self) -> Result<(), BanksClientError> {
+        // the farm's accounts come along once the pool has one
+        let farm = farm_address(&self.config).0;
+        let farm = self.ctx.banks_client.get_account(farm).await.unwrap().map(|_| farm);
+        let ix = self.close_pool_ix(farm);
+        self.send(&[ix]).await
+    }
+
+    fn close_pool_ix(&self, farm: Option<Pubkey>) -> Instruction {
+        self.ix(
+            amm::accounts::ClosePool {
+                authority: self.ctx.payer.pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                oracle: self.oracle,
+                pool_entry: pool_entry_address(&self.mint_x, &self.mint_y, FEE).0,
+                pool_index: pool_index_address(0).0,
+                mint_lp: self.mint_lp,
+                locked_lp: self.locked_lp,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                treasury_x: self.user_x,
+                treasury_y: self.user_y,
+                farm,
+                farm_lp_vault: farm.map(|farm| self.farm_vault(&farm, &self.mint_lp)),
+                reward_mint: farm.map(|_| self.mint_x),
+                reward_vault: farm.map(|farm| self.farm_vault(&farm, &self.mint_x)),
+                treasury_reward: farm.map(|_| self.user_x),
+                reward_token_program: farm.map(|_| self.token_program_x),
+                associated_token_program: spl_associated_token_account::ID,
+                token_program: spl_token::ID,
+                token_program_x: self.token_program_x,
+                token_program_y: self.token_program_y,
+            },
+            amm::instruction::ClosePool {}
+        )
+    }
+
+    fn flash_borrow_ix(&self, is_x: bool, amount: u64) -> Instruction {
+        self.ix(
+            amm::accounts::FlashBorrow {
+                user: self.ctx.payer.pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                user_token: match is_x {
+                    true => self.user_x,
+                    false => self.user_y,
+                },
+                instruction_sysvar: sysvar::instructions::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                token_program_x: self.token_program_x,
+                token_program_y: self.token_program_y,
+            },
+            amm::instruction::FlashBorrow { is_x, amount }
+        )
+    }
+
+    fn flash_repay_ix(&self, is_x: bool) -> Instruction {
+        self.ix(
+            amm::accounts::FlashRepay {
+                user: self.ctx.payer.pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                user_token: match is_x {
+                    true => self.user_x,
+                    false => self.user_y,
+                },
+                associated_token_program: spl_associated_token_account::ID,
+                token_program_x: self.token_program_x,
+                token_program_y: self.token_program_y,
+            },
+            amm::instruction::FlashRepay {}
+        )
+    }
+
+    async fn update_config(&mut self, data: impl InstructionData) -> Result<(), BanksClientError> {
+        let ix = self.ix(
+            amm::accounts::UpdateConfig { authority: self.ctx.payer.pubkey(), config: self.config },
+            data
+        );
+        self.send(&[ix]).await
+    }
+
+    // pays the accrued protocol fees into the user's accounts, the user being the authority
+    async fn collect_protocol_fees(&mut self) -> Result<(), BanksClientError> {
+        let ix = self.ix(
+            amm::accounts::CollectProtocolFees {
+                authority: self.ctx.payer.pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                treasury_x: self.user_x,
+                treasury_y: self.user_y,
+                associated_token_program: spl_associated_token_account::ID,
+                token_program_x: self.token_program_x,
+                token_program_y: self.token_program_y,
+            },
+            amm::instruction::CollectProtocolFees {}
+        );
+        self.send(&[ix]).await
+    }
+
+    async fn add_to_allowlist(&mut self, can_deposit: bool, can_swap: bool) {
+        let user = self.ctx.payer.pubkey();
+        let entry = allowlist_entry_address(&self.config, &user).0;
+        let ix = self.ix(
+            amm::accounts::AddToAllowlist {
+                authority: user,
+                config: self.config,
+                allowlist_entry: entry,
+                system_program: system_program::ID,
+            },
+            amm::instruction::AddToAllowlist { user, can_deposit, can_swap }
+        );
+        self.send(&[ix]).await.unwrap();
+        self.allowlist_entry = Some(entry);
+    }
+
+    async fn remove_from_allowlist(&mut self) -> Result<(), BanksClientError> {
+        let entry = self.allowlist_entry.take().unwrap();
+        let ix = self.ix(
+            amm::accounts::RemoveFromAllowlist {
+                authority: self.ctx.payer.pubkey(),
+                config: self.config,
+                allowlist_entry: entry,
+            },
+            amm::instruction::RemoveFromAllowlist {}
+        );
+        self.send(&[ix]).await
+    }
+
+    // a farm on the pool's LP paying out mint x
+    async fn create_farm(&mut self, reward_rate: u64) -> Pubkey {
+        let farm = farm_address(&self.config).0;
+        let ix = self.ix(
+            amm::accounts::CreateFarm {
+                authority: self.ctx.payer.pubkey(),
+                config: self.config,
+                mint_lp: self.mint_lp,
+                reward_mint: self.mint_x,
+                farm,
+                lp_vault: self.farm_vault(&farm, &self.mint_lp),
+                reward_vault: self.farm_vault(&farm, &self.mint_x),
+                system_program: system_program::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                token_program: spl_token::ID,
+                reward_token_program: self.token_program_x,
+            },
+            amm::instruction::CreateFarm { reward_rate }
+        );
+        self.send(&[ix]).await.unwrap();
+        farm
+    }
+
+    fn farm_vault(&self, farm: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(farm, mint, &self.token_program(mint))
+    }
+
+    // the LP mint is always a classic SPL mint
+    fn token_program(&self, mint: &Pubkey) -> Pubkey {
+        match *mint {
+            mint if mint == self.mint_x => self.token_program_x,
+            mint if mint == self.mint_y => self.token_program_y,
+            _ => spl_token::ID,
+        }
+    }
+
+    async fn fund_farm(&mut self, farm: Pubkey, amount: u64) -> Result<(), BanksClientError> {
+        let ix = self.ix(
+            amm::accounts::FundFarm {
+                funder: self.ctx.payer.pubkey(),
+                farm,
+                reward_mint: self.mint_x,
+                funder_reward: self.user_x,
+                reward_vault: self.farm_vault(&farm, &self.mint_x),
+                associated_token_program: spl_associated_token_account::ID,
+                reward_token_program: self.token_program_x,
+            },
+            amm::instruction::FundFarm { amount }
+        );
+        self.send(&[ix]).await
+    }
+
+    fn stake_lp_accounts(&self, farm: Pubkey) -> amm::accounts::StakeLp {
+        let user = self.ctx.payer.pubkey();
+        amm::accounts::StakeLp {
+            user,
+            config: self.config,
+            mint_lp: self.mint_lp,
+            farm,
+            user_stake: user_stake_address(&farm, &user).0,
+            user_lp: self.user_lp,
+            lp_vault: self.farm_vault(&farm, &self.mint_lp),
+            system_program: system_program::ID,
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: spl_token::ID,
+        }
+    }
+
+    async fn stake_lp(&mut self, farm: Pubkey, amount: u64) -> Result<(), BanksClientError> {
+        let ix = self.ix(self.stake_lp_accounts(farm), amm::instruction::StakeLp { amount });
+        self.send(&[ix]).await
+    }
+
+    async fn unstake_lp(&mut self, farm: Pubkey, amount: u64) -> Result<(), BanksClientError> {
+        let ix = self.ix(self.stake_lp_accounts(farm), amm::instruction::UnstakeLp { amount });
+        self.send(&[ix]).await
+    }
+
+    async fn claim(&mut self, farm: Pubkey) -> Result<(), BanksClientError> {
+        let user = self.ctx.payer.pubkey();
+        let ix = self.ix(
+            amm::accounts::Claim {
+                user,
+                farm,
+                user_stake: user_stake_address(&farm, &user).0,
+                reward_mint: self.mint_x,
+                user_reward: self.user_x,
+                reward_vault: self.farm_vault(&farm, &self.mint_x),
+                system_program: system_program::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                reward_token_program: self.token_program_x,
+            },
+            amm::instruction::Claim {}
+        );
+        self.send(&[ix]).await
+    }
+
+    // moves the clock `seconds` ahead
+    async fn warp(&mut self, seconds: i64) {
+        let mut clock = self.ctx.banks_client.get_sysvar::<Clock>().await.unwrap();
+        clock.unix_timestamp += seconds;
+        self.ctx.set_sysvar(&clock);
+    }
+
+    async fn data(&mut self, address: Pubkey) -> Vec<u8> {
+        self.ctx.banks_client.get_account(address).await.unwrap().unwrap().data
+    }
+
+    async fn balance(&mut self, token_account: Pubkey) -> u64 {
+        let data = self.data(token_account).await;
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data).unwrap().base.amount
+    }
+
+    async fn lp_supply(&mut self) -> u64 {
+        let data = self.data(self.mint_lp).await;
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data).unwrap().base.supply
+    }
+
+    async fn config(&mut self) -> Config {
+        Config::try_deserialize(&mut self.data(self.config).await.as_slice()).unwrap()
+    }
+
+    // LP reserves, i.e. the vaults net of accrued protocol fees
+    async fn reserves(&mut self) -> (u64, u64) {
+        let (vault_x, vault_y) = (self.balance(self.vault_x).await, self.balance(self.vault_y).await);
+        self.config().await.reserves(vault_x, vault_y).unwrap()
+    }
+
+    // user balances, LP reserves and LP supply
+    async fn snapshot(&mut self) -> (u64, u64, u64, u64, u64) {
+        let (x, y) = self.reserves().await;
+        (self.balance(self.user_x).await, self.balance(self.user_y).await, x, y, self.lp_supply().await)
+    }
+
+    // a pool holding (x, y) with all but the locked LP owned by the user
+    async fn funded(x: u64, y: u64) -> Self {
+        Self::funded_with(CurveType::ConstantProduct, x, y).await
+    }
+
+    async fn funded_with(curve: CurveType, x: u64, y: u64) -> Self {
+        let mut pool = Pool::with_curve(curve).await;
+        pool.deposit(0, x, y).await.unwrap();
+        pool
+    }
+
+    // compute units `ixs` use, run with the most a transaction can ask for
+    async fn compute_units(&mut self, ixs: &[Instruction]) -> u64 {
+        let mut all = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_400_000)];
+        all.extend_from_slice(ixs);
+        let blockhash = self.ctx.banks_client.get_latest_blockhash().await.unwrap();
+        let tx = Transaction::new_signed_with_payer(
+            &all,
+            Some(&self.ctx.payer.pubkey()),
+            &[&self.ctx.payer],
+            blockhash
+        );
+        let simulation = self.ctx.banks_client.simulate_transaction(tx).await.unwrap();
+        simulation.result.unwrap().unwrap();
+        simulation.simulation_details.unwrap().units_consumed
+    }
+}
+
+// fails unless `res` is the program rejecting the transaction with `expected`
+fn assert_amm_error(res: Result<(), BanksClientError>, expected: AmmError) {
+    match res.expect_err("transaction succeeded").unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+            assert_eq!(code, u32::from(expected));
+        }
+        err => panic!("unexpected error {:?}", err),
+    }
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap()
+}
+
+#[tokio::test]
+async fn initialize_registers_pool() {
+    let mut pool = Pool::new().await;
+    let config = pool.config().await;
+    assert_eq!(config.mint_x, pool.mint_x);
+    assert_eq!(config.mint_y, pool.mint_y);
+    assert_eq!(config.fee, FEE);
+    assert_eq!(config.authority, Some(pool.ctx.payer.pubkey()));
+
+    let registry = registry_address().0;
+    let registry = Registry::try_deserialize(&mut pool.data(registry).await.as_slice()).unwrap();
+    assert_eq!(registry.pool_count, 1);
+    assert_eq!(pool.lp_supply().await, 0);
+
+    // the pool can be found by index as well as by pair and creation fee tier
+    let index = pool.data(pool_index_address(0).0).await;
+    let index = PoolIndex::try_deserialize(&mut index.as_slice()).unwrap();
+    let entry = pool.data(index.pool_entry).await;
+    let entry = PoolEntry::try_deserialize(&mut entry.as_slice()).unwrap();
+    assert_eq!(index.config, pool.config);
+    assert_eq!(index.pool_entry, pool_entry_address(&pool.mint_x, &pool.mint_y, FEE).0);
+    assert_eq!((entry.config, entry.index, entry.fee), (pool.config, 0, FEE));
+
+    // changing the fee leaves the pool under the tier it was created at
+    pool.update_config(amm::instruction::UpdateFee { fee: FEE + 1 }).await.unwrap();
+    assert_eq!(pool.config().await.fee, FEE + 1);
+    let entry = pool.data(index.pool_entry).await;
+    assert_eq!(PoolEntry::try_deserialize(&mut entry.as_slice()).unwrap().fee, FEE);
+}
+
+#[tokio::test]
+async fn initial_deposit_locks_minimum_liquidity() {
+    let mut pool = Pool::new().await;
+    // sqrt(4e12 * 1e12) = 2e12
+    let (x, y) = (4_000_000_000_000, 1_000_000_000_000);
+    assert!(pool.deposit(2_000_000_000_000, x, y).await.is_err());
+    pool.deposit(0, x, y).await.unwrap();
+
+    assert_eq!(pool.lp_supply().await, 2_000_000_000_000);
+    assert_eq!(pool.balance(pool.locked_lp).await, MINIMUM_LIQUIDITY);
+    assert_eq!(pool.balance(pool.user_lp).await, 2_000_000_000_000 - MINIMUM_LIQUIDITY);
+    assert_eq!(pool.reserves().await, (x, y));
+}
+
+#[tokio::test]
+async fn deposit_swap_withdraw() {
+    let mut pool = Pool::funded(1_000_000_000_000, 1_000_000_000_000).await;
+
+    // proportional deposit within the limits
+    let supply = pool.lp_supply().await;
+    assert!(pool.deposit(supply / 10, 1, 1).await.is_err());
+    pool.deposit(supply / 10, u64::MAX, u64::MAX).await.unwrap();
+    assert_eq!(pool.lp_supply().await, supply + supply / 10);
+
+    // the swap pays out exactly what the curve quotes
+    let (x, y) = pool.reserves().await;
+    let amount_in = 10_000_000_000;
+    let expected = CurveType::ConstantProduct.swap(x, y, FEE, true, amount_in).unwrap();
+    assert!(pool.swap(true, amount_in, expected.withdraw + 1).await.is_err());
+    let user_y = pool.balance(pool.user_y).await;
+    pool.swap(true, amount_in, expected.withdraw).await.unwrap();
+    assert_eq!(pool.balance(pool.user_y).await - user_y, expected.withdraw);
+    assert_eq!(pool.reserves().await, (x + amount_in, y - expected.withdraw));
+
+    // withdrawing everything leaves only the locked liquidity behind
+    let lp = pool.balance(pool.user_lp).await;
+    let (x, y) = pool.reserves().await;
+    assert!(pool.withdraw(lp, x, y).await.is_err());
+    pool.withdraw(lp, 0, 0).await.unwrap();
+    assert_eq!(pool.balance(pool.user_lp).await, 0);
+    assert_eq!(pool.lp_supply().await, MINIMUM_LIQUIDITY);
+    let (rest_x, rest_y) = pool.reserves().await;
+    assert!(rest_x > 0 && rest_y > 0);
+}
+
+#[tokio::test]
+async fn swap_exact_out_pays_out_exactly_the_amount_asked_for() {
+    let mut pool = Pool::funded(1_000_000_000_000, 1_000_000_000_000).await;
+    let (user_x, user_y, x, y, supply) = pool.snapshot().await;
+    let amount_out = 10_000_000_000;
+    let expected = CurveType::ConstantProduct.swap_exact_out(x, y, FEE, true, amount_out).unwrap();
+    assert_eq!(expected.withdraw, amount_out);
+
+    // max_in one short of what the curve charges
+    assert_amm_error(
+        pool.swap_exact_out(true, amount_out, expected.deposit - 1).await,
+        AmmError::SlippageExceeded
+    );
+    pool.swap_exact_out(true, amount_out, expected.deposit).await.unwrap();
+    assert_eq!(pool.snapshot().await, (
+        user_x - expected.deposit,
+        user_y + amount_out,
+        x + expected.deposit,
+        y - amount_out,
+        supply,
+    ));
+}
+
+#[tokio::test]
+async fn locked_pool_rejects_trades() {
+    let mut pool = Pool::funded(1_000_000_000_000, 1_000_000_000_000).await;
+    let lp = pool.balance(pool.user_lp).await / 10;
+    pool.update_config(amm::instruction::Lock {}).await.unwrap();
+    assert_amm_error(pool.swap(true, 1_000_000, 0).await, AmmError::PoolLocked);
+    assert_amm_error(pool.swap_exact_out(true, 1_000_000, u64::MAX).await, AmmError::PoolLocked);
+    assert_amm_error(pool.deposit(lp, u64::MAX, u64::MAX).await, AmmError::PoolLocked);
+    assert_amm_error(pool.withdraw(lp, 0, 0).await, AmmError::PoolLocked);
+
+    pool.update_config(amm::instruction::Unlock {}).await.unwrap();
+    pool.swap(true, 1_000_000, 0).await.unwrap();
+    pool.withdraw(lp, 0, 0).await.unwrap();
+}
+
+#[tokio::test]
+async fn protocol_fees_are_collected_by_the_authority() {
+    let mut pool = Pool::funded(1_000_000_000_000, 1_000_000_000_000).await;
+    // a fifth of each swap fee
+    pool.update_config(amm::instruction::UpdateProtocolFee { protocol_fee: 2_000 }).await.unwrap();
+
+    let (x, y) = pool.reserves().await;
+    let amount_in = 10_000_000_000;
+    let expected = CurveType::ConstantProduct.swap(x, y, FEE, true, amount_in).unwrap();
+    let protocol = expected.fee / 5;
+    pool.swap(true, amount_in, expected.withdraw).await.unwrap();
+    let config = pool.config().await;
+    assert_eq!((config.protocol_fees_x, config.protocol_fees_y), (protocol, 0));
+    // the protocol's share sits in the vault but isn't the LPs'
+    let reserves = (x + amount_in - protocol, y - expected.withdraw);
+    assert_eq!(pool.reserves().await, reserves);
+
+    let user_x = pool.balance(pool.user_x).await;
+    pool.collect_protocol_fees().await.unwrap();
+    assert_eq!(pool.balance(pool.user_x).await, user_x + protocol);
+    let config = pool.config().await;
+    assert_eq!((config.protocol_fees_x, config.protocol_fees_y), (0, 0));
+    assert_eq!(pool.reserves().await, reserves);
+}
+
+#[tokio::test]
+async fn imbalanced_withdraw_burns_what_the_curve_quotes() {
+    let curve = CurveType::StableSwap { amp: 100 };
+    let mut pool = Pool::funded_with(curve, 1_000_000_000_000, 1_000_000_000_000).await;
+    let (x, y) = pool.reserves().await;
+    let supply = pool.lp_supply().await;
+    let (amount_x, amount_y) = (100_000_000_000, 0);
+    let lp = curve.withdraw_imbalanced_lp(x, y, FEE, supply, amount_x, amount_y).unwrap();
+
+    // a twentieth of the pool's value from one side costs more than a twentieth of the LP
+    assert!(lp > supply / 20);
+    assert_amm_error(pool.withdraw_imbalanced(amount_x, amount_y, lp - 1).await, AmmError::SlippageExceeded);
+
+    let (user_x, user_y, user_lp) = (
+        pool.balance(pool.user_x).await,
+        pool.balance(pool.user_y).await,
+        pool.balance(pool.user_lp).await,
+    );
+    pool.withdraw_imbalanced(amount_x, amount_y, lp).await.unwrap();
+    assert_eq!(pool.balance(pool.user_x).await, user_x + amount_x);
+    assert_eq!(pool.balance(pool.user_y).await, user_y);
+    assert_eq!(pool.balance(pool.user_lp).await, user_lp - lp);
+    assert_eq!(pool.reserves().await, (x - amount_x, y));
+}
+
+#[tokio::test]
+async fn close_pool_returns_dust_and_rent() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    assert!(pool.close_pool().await.is_err());
+
+    let lp = pool.balance(pool.user_lp).await;
+    pool.withdraw(lp, 0, 0).await.unwrap();
+    let (user_x, user_y) = (pool.balance(pool.user_x).await, pool.balance(pool.user_y).await);
+    let (dust_x, dust_y) = (pool.balance(pool.vault_x).await, pool.balance(pool.vault_y).await);
+    assert!(dust_x > 0 && dust_y > 0);
+    pool.close_pool().await.unwrap();
+
+    // whatever backed the locked liquidity is swept back to the authority
+    assert_eq!(pool.balance(pool.user_x).await, user_x + dust_x);
+    assert_eq!(pool.balance(pool.user_y).await, user_y + dust_y);
+    assert_eq!(pool.lp_supply().await, 0);
+    let registered = [pool_entry_address(&pool.mint_x, &pool.mint_y, FEE).0, pool_index_address(0).0];
+    for address in [pool.config, pool.oracle, pool.vault_x, pool.vault_y, pool.locked_lp]
+        .into_iter()
+        .chain(registered)
+    {
+        assert!(pool.ctx.banks_client.get_account(address).await.unwrap().is_none());
+    }
+}
+
+#[tokio::test]
+async fn close_pool_sweeps_the_farm() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    let farm = pool.create_farm(1_000).await;
+    let lp_vault = pool.farm_vault(&farm, &pool.mint_lp);
+    let reward_vault = pool.farm_vault(&farm, &pool.mint_x);
+    pool.fund_farm(farm, 150_000).await.unwrap();
+    let lp = pool.balance(pool.user_lp).await;
+    pool.stake_lp(farm, lp).await.unwrap();
+    pool.warp(100).await;
+
+    // staked LP is still liquidity
+    assert_amm_error(pool.close_pool().await, AmmError::PoolNotEmpty);
+    pool.unstake_lp(farm, lp).await.unwrap();
+    pool.withdraw(lp, 0, 0).await.unwrap();
+
+    // the farm can't be left behind with the config gone
+    let ix = pool.close_pool_ix(None);
+    assert_amm_error(pool.send(&[ix]).await, AmmError::MissingFarmAccounts);
+
+    // rewards earned but never claimed go out with the undistributed ones
+    let rewards = pool.balance(reward_vault).await;
+    assert_eq!(rewards, 150_000);
+    let (user_x, dust_x) = (pool.balance(pool.user_x).await, pool.balance(pool.vault_x).await);
+    pool.close_pool().await.unwrap();
+    assert_eq!(pool.balance(pool.user_x).await, user_x + dust_x + rewards);
+    for address in [farm, lp_vault, reward_vault] {
+        assert!(pool.ctx.banks_client.get_account(address).await.unwrap().is_none());
+    }
+}
+
+#[tokio::test]
+async fn allowlist_gates_deposit_and_swap() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    pool.update_config(amm::instruction::SetAllowlist {
+        allowlist: Some(AllowlistMode { deposit: true, swap: true }),
+    }).await.unwrap();
+    assert!(pool.swap(true, 1_000, 0).await.is_err());
+    assert!(pool.deposit(1_000, u64::MAX, u64::MAX).await.is_err());
+
+    pool.add_to_allowlist(false, true).await;
+    pool.swap(true, 1_000, 0).await.unwrap();
+    assert!(pool.deposit(1_000, u64::MAX, u64::MAX).await.is_err());
+
+    // plain withdrawals never need an entry, single sided ones swap and do
+    let entry = pool.allowlist_entry.take();
+    pool.withdraw(1_000, 0, 0).await.unwrap();
+    assert_amm_error(pool.withdraw_single(true, 1_000, 0).await, AmmError::NotAllowlisted);
+    pool.allowlist_entry = entry;
+    pool.withdraw_single(true, 1_000, 0).await.unwrap();
+
+    pool.remove_from_allowlist().await.unwrap();
+    assert!(pool.ctx.banks_client.get_account(entry.unwrap()).await.unwrap().is_none());
+    assert_amm_error(pool.swap(true, 1_000, 0).await, AmmError::NotAllowlisted);
+}
+
+#[tokio::test]
+async fn zaps_and_imbalanced_withdrawals_need_swap_permission() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    pool.update_config(amm::instruction::SetAllowlist {
+        allowlist: Some(AllowlistMode { deposit: true, swap: true }),
+    }).await.unwrap();
+
+    // with no entry at all
+    assert_amm_error(pool.deposit_single(true, 1_000, 0).await, AmmError::NotAllowlisted);
+    assert_amm_error(pool.withdraw_imbalanced(1_000, 0, u64::MAX).await, AmmError::NotAllowlisted);
+
+    // LP can change hands, so being allowed to deposit isn't enough to trade through a zap
+    pool.add_to_allowlist(true, false).await;
+    pool.deposit(1_000, u64::MAX, u64::MAX).await.unwrap();
+    pool.withdraw(1_000, 0, 0).await.unwrap();
+    assert_amm_error(pool.deposit_single(true, 1_000, 0).await, AmmError::NotAllowlisted);
+    assert_amm_error(pool.withdraw_imbalanced(1_000, 0, u64::MAX).await, AmmError::NotAllowlisted);
+
+    pool.add_to_allowlist(true, true).await;
+    pool.deposit_single(true, 1_000, 0).await.unwrap();
+    pool.withdraw_imbalanced(1_000, 0, u64::MAX).await.unwrap();
+}
+
+#[tokio::test]
+async fn close_pool_waits_for_allowlist_entries() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    let lp = pool.balance(pool.user_lp).await;
+    pool.withdraw(lp, 0, 0).await.unwrap();
+
+    // updating an entry doesn't count it twice
+    pool.add_to_allowlist(true, false).await;
+    pool.add_to_allowlist(true, true).await;
+    assert_eq!(pool.config().await.allowlist_entries, 1);
+    assert_amm_error(pool.close_pool().await, AmmError::AllowlistNotEmpty);
+
+    pool.remove_from_allowlist().await.unwrap();
+    assert_eq!(pool.config().await.allowlist_entries, 0);
+    pool.close_pool().await.unwrap();
+}
+
+#[tokio::test]
+async fn route_cant_swap_through_a_pool_twice_in_a_row() {
+    let mut pool = Pool::funded(1_000_000_000_000, 1_000_000_000_000).await;
+    let (x, y) = pool.reserves().await;
+    let user_y = pool.balance(pool.user_y).await;
+    pool.swap_route(true, 1, 1_000_000, 0).await.unwrap();
+    let (x1, y1) = pool.reserves().await;
+    assert_eq!(x1, x + 1_000_000);
+    assert_eq!(pool.balance(pool.user_y).await, user_y + (y - y1));
+
+    // x -> y -> x through the same pool would price the second leg against stale reserves
+    assert_amm_error(pool.swap_route(true, 2, 1_000_000, 0).await, AmmError::RepeatedPool);
+    assert_eq!(pool.reserves().await, (x1, y1));
+}
+
+#[tokio::test]
+async fn referrer_gets_share_of_swap_fee() {
+    let mut pool = Pool::funded(1_000_000_000_000, 1_000_000_000_000).await;
+    pool.update_config(amm::instruction::UpdateReferralFee { referral_fee: 2_500 }).await.unwrap();
+
+    let user = pool.ctx.payer.pubkey();
+    let wallet = Pubkey::new_unique();
+    let referrer = get_associated_token_address_with_program_id(&wallet, &pool.mint_x, &spl_token::ID);
+    let create = spl_associated_token_account::instruction::create_associated_token_account(
+        &user,
+        &wallet,
+        &pool.mint_x,
+        &spl_token::ID
+    );
+    pool.send(&[create]).await.unwrap();
+
+    let (x, y) = pool.reserves().await;
+    let amount_in = 10_000_000_000;
+    let expected = CurveType::ConstantProduct.swap(x, y, FEE, true, amount_in).unwrap();
+    let cut = expected.fee / 4;
+
+    // an account of the output mint can't take the referral
+    pool.referrer = Some(pool.user_y);
+    assert!(pool.swap(true, amount_in, 0).await.is_err());
+    // nor can the trader's own account
+    pool.referrer = Some(pool.user_x);
+    assert_amm_error(pool.swap(true, amount_in, 0).await, AmmError::SelfReferral);
+
+    pool.referrer = Some(referrer);
+    pool.swap(true, amount_in, expected.withdraw).await.unwrap();
+    assert_eq!(pool.balance(referrer).await, cut);
+    assert_eq!(pool.reserves().await, (x + amount_in - cut, y - expected.withdraw));
+}
+
+#[tokio::test]
+async fn token_2022_transfer_fees_are_charged_outside_the_pool() {
+    // x is a Token-2022 mint withholding 1% of every transfer, y a classic one
+    let mut pool = Pool::with_transfer_fees(CurveType::ConstantProduct, [Some(100), None]).await;
+    let fee = |amount: u64| amount.div_ceil(100);
+    pool.deposit(0, 1_000_000_000_000, 1_000_000_000_000).await.unwrap();
+    assert_eq!(pool.reserves().await, (990_000_000_000, 1_000_000_000_000));
+
+    // a swap is priced on what reaches the vault
+    let (user_x, user_y, x, y, _) = pool.snapshot().await;
+    let amount_in = 10_000_000_000;
+    let expected = CurveType::ConstantProduct.swap(x, y, FEE, true, amount_in - fee(amount_in)).unwrap();
+    pool.swap(true, amount_in, expected.withdraw).await.unwrap();
+    assert_eq!(pool.snapshot().await, (
+        user_x - amount_in,
+        user_y + expected.withdraw,
+        x + amount_in - fee(amount_in),
+        y - expected.withdraw,
+        pool.lp_supply().await,
+    ));
+
+    // and min_out is checked against what reaches the user
+    let (user_x, _, x, y, _) = pool.snapshot().await;
+    let expected = CurveType::ConstantProduct.swap(x, y, FEE, false, amount_in).unwrap();
+    let received = expected.withdraw - fee(expected.withdraw);
+    assert_amm_error(pool.swap(false, amount_in, received + 1).await, AmmError::SlippageExceeded);
+    pool.swap(false, amount_in, received).await.unwrap();
+    assert_eq!(pool.balance(pool.user_x).await, user_x + received);
+
+    // withdrawals pay the fee on the way out as well
+    let (user_x, user_y, x, y, supply) = pool.snapshot().await;
+    let lp = pool.balance(pool.user_lp).await / 2;
+    let amounts = CurveType::ConstantProduct.withdraw_amounts(x, y, supply, lp).unwrap();
+    pool.withdraw(lp, amounts.x - fee(amounts.x), amounts.y).await.unwrap();
+    assert_eq!(pool.balance(pool.user_x).await, user_x + amounts.x - fee(amounts.x));
+    assert_eq!(pool.balance(pool.user_y).await, user_y + amounts.y);
+}
+
+#[tokio::test]
+async fn expired_swap_is_rejected() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    let clock = pool.ctx.banks_client.get_sysvar::<Clock>().await.unwrap();
+    assert!(pool.swap_until(true, 1_000, 0, Some(clock.unix_timestamp - 1)).await.is_err());
+    pool.swap_until(true, 1_000, 0, Some(clock.unix_timestamp + 60)).await.unwrap();
+}
+
+#[tokio::test]
+async fn flash_loan_repays_principal_and_fee() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    let (vault_x, user_x) = (pool.balance(pool.vault_x).await, pool.balance(pool.user_x).await);
+    let amount = 500_000_000;
+    let fee = ((amount as u128) * (FEE as u128)).div_ceil(10_000) as u64;
+    let ixs = [pool.flash_borrow_ix(true, amount), pool.flash_repay_ix(true)];
+    pool.send(&ixs).await.unwrap();
+
+    assert_eq!(pool.balance(pool.vault_x).await, vault_x + fee);
+    assert_eq!(pool.balance(pool.user_x).await, user_x - fee);
+    assert!(pool.config().await.flash_loan.is_none());
+}
+
+#[tokio::test]
+async fn flash_borrow_needs_a_later_repay() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    let borrow = pool.flash_borrow_ix(true, 1_000);
+    assert_amm_error(pool.send(std::slice::from_ref(&borrow)).await, AmmError::MissingFlashRepay);
+
+    // a repay placed before the borrow doesn't count
+    let repay = pool.flash_repay_ix(true);
+    assert_amm_error(pool.send(&[repay, borrow]).await, AmmError::MissingFlashRepay);
+}
+
+#[tokio::test]
+async fn flash_repay_for_another_pool_is_not_accepted() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    // same instruction, but the config the borrow looks for at index 3 is some other pool's
+    let mut repay = pool.flash_repay_ix(true);
+    repay.accounts[3].pubkey = Pubkey::new_unique();
+    let ixs = [pool.flash_borrow_ix(true, 1_000), repay];
+    assert_amm_error(pool.send(&ixs).await, AmmError::MissingFlashRepay);
+}
+
+#[tokio::test]
+async fn flash_borrows_cant_share_a_repay() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    let ixs = [
+        pool.flash_borrow_ix(true, 1_000),
+        pool.flash_borrow_ix(false, 1_000),
+        pool.flash_repay_ix(true),
+    ];
+    assert_amm_error(pool.send(&ixs).await, AmmError::FlashLoanActive);
+}
+
+#[tokio::test]
+async fn flash_repay_without_the_fee_fails() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    // leave the user with nothing on x, so the borrowed principal is all there is to repay with
+    let user = pool.ctx.payer.pubkey();
+    let user_x = pool.balance(pool.user_x).await;
+    let burn = spl_token::instruction
+        ::burn(&spl_token::ID, &pool.user_x, &pool.mint_x, &user, &[], user_x)
+        .unwrap();
+    pool.send(&[burn]).await.unwrap();
+
+    let vault_x = pool.balance(pool.vault_x).await;
+    let ixs = [pool.flash_borrow_ix(true, 1_000_000), pool.flash_repay_ix(true)];
+    match pool.send(&ixs).await.expect_err("transaction succeeded").unwrap() {
+        TransactionError::InstructionError(2, InstructionError::Custom(code)) => {
+            assert_eq!(code, spl_token::error::TokenError::InsufficientFunds as u32);
+        }
+        err => panic!("unexpected error {:?}", err),
+    }
+    // the whole transaction is rolled back, loan included
+    assert_eq!(pool.balance(pool.vault_x).await, vault_x);
+    assert!(pool.config().await.flash_loan.is_none());
+}
+
+#[tokio::test]
+async fn farm_pays_stakers_what_was_funded() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    let farm = pool.create_farm(1_000).await;
+    let vault_lp = pool.farm_vault(&farm, &pool.mint_lp);
+    pool.fund_farm(farm, 150_000).await.unwrap();
+
+    let lp = pool.balance(pool.user_lp).await;
+    pool.stake_lp(farm, lp).await.unwrap();
+    assert_eq!(pool.balance(vault_lp).await, lp);
+
+    pool.warp(100).await;
+    let user_x = pool.balance(pool.user_x).await;
+    pool.claim(farm).await.unwrap();
+    let first = pool.balance(pool.user_x).await - user_x;
+    // 100s at 1_000 a second, less the rounding of the per share reward
+    assert!((99_999..=100_000).contains(&first), "claimed {}", first);
+
+    // unstaking settles what was earned until then, nothing accrues after
+    pool.warp(20).await;
+    pool.unstake_lp(farm, lp).await.unwrap();
+    assert_eq!(pool.balance(pool.user_lp).await, lp);
+    pool.warp(1_000).await;
+    assert_amm_error(pool.unstake_lp(farm, 1).await, AmmError::InsufficientStake);
+    let user_x = pool.balance(pool.user_x).await;
+    pool.claim(farm).await.unwrap();
+    let second = pool.balance(pool.user_x).await - user_x;
+    assert!((19_999..=20_000).contains(&second), "claimed {}", second);
+
+    let farm_state = Farm::try_deserialize(&mut pool.data(farm).await.as_slice()).unwrap();
+    assert_eq!(farm_state.total_staked, 0);
+    assert_eq!(farm_state.undistributed, 30_000);
+    let user_stake = user_stake_address(&farm, &pool.ctx.payer.pubkey()).0;
+    let user_stake = UserStake::try_deserialize(&mut pool.data(user_stake).await.as_slice()).unwrap();
+    assert_eq!((user_stake.amount, user_stake.pending), (0, 0));
+}
+
+// bands `pool` to within 1% of a price of 1 and returns the reference account
+async fn set_unit_price_band(pool: &mut Pool) -> Pubkey {
+    // stand-in for an oracle feed: a plain account holding a Q64.64 price of 1
+    let reference = Pubkey::new_unique();
+    let rent = pool.ctx.banks_client.get_rent().await.unwrap();
+    pool.ctx.set_account(&reference, &(Account {
+        lamports: rent.minimum_balance(16),
+        data: (1u128 << 64).to_le_bytes().to_vec(),
+        owner: system_program::ID,
+        executable: false,
+        rent_epoch: 0,
+    }).into());
+    pool.update_config(amm::instruction::SetPriceBand {
+        price_band: Some(PriceBand { reference, max_deviation: 100 }),
+    }).await.unwrap();
+    reference
+}
+
+#[tokio::test]
+async fn price_band_halts_swaps_that_move_price_too_far() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    let reference = set_unit_price_band(&mut pool).await;
+
+    // the reference account has to come along
+    assert!(pool.swap(true, 1_000, 0).await.is_err());
+
+    pool.reference_price = Some(reference);
+    pool.swap(true, 1_000_000, 0).await.unwrap();
+    // roughly 20% off the reference
+    assert!(pool.swap(true, 100_000_000, 0).await.is_err());
+
+    pool.update_config(amm::instruction::SetPriceBand { price_band: None }).await.unwrap();
+    pool.swap(true, 100_000_000, 0).await.unwrap();
+}
+
+#[tokio::test]
+async fn price_band_halts_zaps_that_move_price_too_far() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    let reference = set_unit_price_band(&mut pool).await;
+    assert_amm_error(
+        pool.deposit_single(true, 1_000_000, 0).await,
+        AmmError::InvalidReferencePrice
+    );
+
+    pool.reference_price = Some(reference);
+    pool.deposit_single(true, 1_000_000, 0).await.unwrap();
+    pool.withdraw_single(true, 1_000_000, 0).await.unwrap();
+
+    // a one sided deposit and withdrawal of a tenth of the pool each move the price ~10%
+    assert_amm_error(
+        pool.deposit_single(true, 100_000_000, 0).await,
+        AmmError::PriceOutOfBand
+    );
+    let lp = pool.lp_supply().await / 10;
+    assert_amm_error(pool.withdraw_single(false, lp, 0).await, AmmError::PriceOutOfBand);
+}
+
+#[tokio::test]
+async fn price_band_halts_imbalanced_withdrawals_that_move_price_too_far() {
+    let mut pool = Pool::funded(1_000_000_000, 1_000_000_000).await;
+    let reference = set_unit_price_band(&mut pool).await;
+    assert_amm_error(
+        pool.withdraw_imbalanced(1_000_000, 0, u64::MAX).await,
+        AmmError::InvalidReferencePrice
+    );
+
+    pool.reference_price = Some(reference);
+    pool.withdraw_imbalanced(1_000_000, 0, u64::MAX).await.unwrap();
+    // taking both sides in step barely moves it, however much is taken
+    pool.withdraw_imbalanced(100_000_000, 100_000_000, u64::MAX).await.unwrap();
+    // a tenth of the pool from one side moves it ~10%
+    assert_amm_error(
+        pool.withdraw_imbalanced(90_000_000, 0, u64::MAX).await,
+        AmmError::PriceOutOfBand
+    );
+}
+
+#[tokio::test]
+async fn zaps_feed_dynamic_fee_volatility() {
+    let mut pool = Pool::funded(1_000_000_000_000, 1_000_000_000_000).await;
+    assert_amm_error(
+        pool.update_config(amm::instruction::EnableDynamicFee {
+            min_fee: 10,
+            max_fee: 500,
+            sensitivity: 100,
+            decay_period: 0,
+        }).await,
+        AmmError::InvalidDecayPeriod
+    );
+    pool.update_config(amm::instruction::EnableDynamicFee {
+        min_fee: 10,
+        max_fee: 500,
+        sensitivity: 100,
+        decay_period: 3_600,
+    }).await.unwrap();
+
+    let volatility = |config: Config| config.dynamic_fee.unwrap().volatility;
+    pool.deposit_single(true, 100_000_000_000, 0).await.unwrap();
+    let after_deposit = volatility(pool.config().await);
+    assert!(after_deposit > 0);
+
+    let lp = pool.balance(pool.user_lp).await / 10;
+    pool.withdraw_single(true, lp, 0).await.unwrap();
+    let after_withdraw = volatility(pool.config().await);
+    assert!(after_withdraw > after_deposit);
+
+    // a lopsided payout moves the price as much as the equivalent swap would
+    pool.withdraw_imbalanced(10_000_000_000, 0, u64::MAX).await.unwrap();
+    assert!(volatility(pool.config().await) > after_withdraw);
+}
+
+// marginal price of x in y, from the partial derivatives of the invariant. StableSwap's
+// is 2·amp·(x + y) + D = 2·amp·D + D³ / (4·x·y) with the program's amp scaling
+fn spot_price(curve: CurveType, x: u64, y: u64) -> f64 {
+    let (x_f, y_f) = (x as f64, y as f64);
+    match curve {
+        CurveType::ConstantProduct => y_f / x_f,
+        CurveType::StableSwap { amp } => {
+            let d = curve.invariant(x, y).unwrap() as f64;
+            let (amp, d3) = (amp as f64, d * d * d);
+            (2.0 * amp + d3 / (4.0 * x_f * x_f * y_f)) / (2.0 * amp + d3 / (4.0 * x_f * y_f * y_f))
+        }
+    }
+}
+
+// worth of the pool in units of one side, at the marginal price
+fn pool_value(curve: CurveType, x: u64, y: u64, is_x: bool) -> f64 {
+    let price = spot_price(curve, x, y);
+    match is_x {
+        true => (x as f64) + (y as f64) / price,
+        false => (y as f64) + (x as f64) * price,
+    }
+}
+
+fn curves() -> impl Strategy<Value = CurveType> {
+    prop_oneof![
+        Just(CurveType::ConstantProduct),
+        Just(CurveType::StableSwap { amp: 1 }),
+        Just(CurveType::StableSwap { amp: 100 }),
+        Just(CurveType::StableSwap { amp: MAX_AMP })
+    ]
+}
+
+#[tokio::test]
+async fn stable_zap_compute_units() {
+    // lopsided reserves and an amount needing all 60 odd rounds of the search
+    let curve = CurveType::StableSwap { amp: 100 };
+    let mut pool = Pool::funded_with(curve, 1_000_000_000, 100_000_000_000_000).await;
+    let ix = pool.deposit_single_ix(true, BALANCE / 2, 0);
+    let units = pool.compute_units(&[ix]).await;
+    assert!(units < 1_400_000, "StableSwap deposit_single used {} compute units", units);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn k_never_decreases(
+        x in 1_000_000u64..1_000_000_000_000,
+        y in 1_000_000u64..1_000_000_000_000,
+        swaps in prop::collection::vec((any::<bool>(), 1u64..10_000_000_000), 1..8),
+    ) {
+        let ks = runtime().block_on(async {
+            let mut pool = Pool::funded(x, y).await;
+            let (x, y) = pool.reserves().await;
+            let mut ks = vec![(x as u128) * (y as u128)];
+            for (is_x, amount) in swaps {
+                // swaps the curve rejects, e.g. for a zero output, just leave k alone
+                if pool.swap(is_x, amount, 0).await.is_ok() {
+                    let (x, y) = pool.reserves().await;
+                    ks.push((x as u128) * (y as u128));
+                }
+            }
+            ks
+        });
+        for k in ks.windows(2) {
+            prop_assert!(k[1] >= k[0], "k dropped from {} to {}", k[0], k[1]);
+        }
+    }
+
+    #[test]
+    fn deposit_withdraw_round_trip_conserves_value(
+        x in 1_000_000u64..1_000_000_000_000,
+        y in 1_000_000u64..1_000_000_000_000,
+        share in 1u64..10_000,
+    ) {
+        let (before, after) = runtime().block_on(async {
+            let mut pool = Pool::funded(x, y).await;
+            let lp = (pool.lp_supply().await / 1_000 * share).max(1);
+            let before = pool.snapshot().await;
+            pool.deposit(lp, u64::MAX, u64::MAX).await.unwrap();
+            pool.withdraw(lp, 0, 0).await.unwrap();
+            (before, pool.snapshot().await)
+        });
+        let (user_x0, user_y0, x0, y0, supply0) = before;
+        let (user_x1, user_y1, x1, y1, supply1) = after;
+        // the round trip can't make the user richer ...
+        prop_assert!(user_x1 <= user_x0 && user_y1 <= user_y0);
+        // ... and rounding only ever goes to the pool, so LP value doesn't drop
+        prop_assert_eq!(supply1, supply0);
+        prop_assert!(x1 >= x0 && y1 >= y0);
+        // tokens are only moved around, never created or lost
+        prop_assert_eq!(user_x0 + x0, user_x1 + x1);
+        prop_assert_eq!(user_y0 + y0, user_y1 + y1);
+    }
+
+    #[test]
+    fn zaps_never_beat_a_balanced_deposit_of_equal_value(
+        curve in curves(),
+        x in 1_000_000u64..1_000_000_000_000,
+        y in 1_000_000u64..1_000_000_000_000,
+        amount in 1u64..1_000_000_000_000,
+        is_x in any::<bool>(),
+    ) {
+        let (minted, deposit_bound, paid, withdraw_bound) = runtime().block_on(async {
+            let mut pool = Pool::funded_with(curve, x, y).await;
+            let (x, y) = pool.reserves().await;
+            let supply = pool.lp_supply().await;
+            // a balanced deposit worth `amount` at the marginal price, free of fees and impact
+            let deposit_bound = (supply as f64) * (amount as f64) / pool_value(curve, x, y, is_x);
+            let lp = pool.balance(pool.user_lp).await;
+            if pool.deposit_single(is_x, amount, 0).await.is_err() {
+                return (0, 0.0, 0, 0.0);
+            }
+            let minted = pool.balance(pool.user_lp).await - lp;
+
+            // and the other way, the LP just minted can't be taken out for more than its share
+            let (x, y) = pool.reserves().await;
+            let supply = pool.lp_supply().await;
+            let withdraw_bound = pool_value(curve, x, y, is_x) * (minted as f64) / (supply as f64);
+            let user_token = match is_x {
+                true => pool.user_x,
+                false => pool.user_y,
+            };
+            let before = pool.balance(user_token).await;
+            if minted == 0 || pool.withdraw_single(is_x, minted, 0).await.is_err() {
+                return (minted, deposit_bound, 0, 0.0);
+            }
+            (minted, deposit_bound, pool.balance(user_token).await - before, withdraw_bound)
+        });
+        prop_assert!((minted as f64) <= deposit_bound * (1.0 + 1e-9), "minted {} of {}", minted, deposit_bound);
+        prop_assert!((paid as f64) <= withdraw_bound * (1.0 + 1e-9), "paid {} of {}", paid, withdraw_bound);
+    }
+
+    #[test]
+    fn zap_search_terminates_on_edge_reserves(
+        curve in curves(),
+        x in prop_oneof![Just(1_000_000u64), Just(100_000_000_000_000), 1_000_000u64..100_000_000_000_000],
+        y in prop_oneof![Just(1_000_000u64), Just(100_000_000_000_000), 1_000_000u64..100_000_000_000_000],
+        amount in prop_oneof![Just(1u64), Just(BALANCE / 2), 1u64..BALANCE / 2],
+        is_x in any::<bool>(),
+    ) {
+        let results = runtime().block_on(async {
+            let mut pool = Pool::funded_with(curve, x, y).await;
+            let deposit = pool.deposit_single(is_x, amount, 0).await;
+            let lp = pool.balance(pool.user_lp).await;
+            let withdraw = pool.withdraw_single(!is_x, (amount % lp).max(1), 0).await;
+            [deposit, withdraw]
+        });
+        // the program may turn a zap down, but it has to get to an answer to do so
+        for res in results {
+            match res.map_err(|err| err.unwrap()) {
+                Ok(()) | Err(TransactionError::InstructionError(_, InstructionError::Custom(_))) => {}
+                Err(err) => prop_assert!(false, "zap failed with {:?}", err),
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use amm::{
+    constants::MINIMUM_LIQUIDITY,
+    error::AmmError,
+    state::{ post_swap_reserves, price_move_bps, Config },
+    utils::isqrt,
+};
+use anchor_lang::{ prelude::ProgramError, AccountDeserialize, Result };
+use anchor_spl::{
+    token_2022::spl_token_2022::{
+        extension::{
+            transfer_fee::{ TransferFee, TransferFeeConfig },
+            BaseStateWithExtensions,
+            StateWithExtensions,
+        },
+        state::Mint as MintState,
+    },
+    token_interface::{ Mint, TokenAccount },
+};
+use constant_product_curve::SwapResult;
+
+// everything a quote depends on, read from the pool's accounts
+#[derive(Clone)]
+pub struct Pool {
+    pub config: Config,
+    pub vault_x: u64,
+    pub vault_y: u64,
+    pub lp_supply: u64,
+    // transfer-fee extension schedule of each side for the quoted epoch, None when there is none
+    pub transfer_fee_x: Option<TransferFee>,
+    pub transfer_fee_y: Option<TransferFee>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapQuote {
+    // sent by the user
+    pub amount_in: u64,
+    // received by the user
+    pub amount_out: u64,
+    // pool fee in the input token, protocol share included
+    pub fee: u64,
+    // how far the swap moves the pool's y/x price, in basis points
+    pub price_impact_bps: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidityQuote {
+    pub lp_amount: u64,
+    // sent by the user on deposits, received by the user on withdrawals
+    pub amount_x: u64,
+    pub amount_y: u64,
+}
+
+impl Pool {
+    pub fn from_accounts(
+        config: &[u8],
+        vault_x: &[u8],
+        vault_y: &[u8],
+        mint_lp: &[u8]
+    ) -> Result<Self> {
+        Ok(Pool {
+            config: Config::try_deserialize(&mut &config[..])?,
+            vault_x: TokenAccount::try_deserialize(&mut &vault_x[..])?.amount,
+            vault_y: TokenAccount::try_deserialize(&mut &vault_y[..])?.amount,
+            lp_supply: Mint::try_deserialize(&mut &mint_lp[..])?.supply,
+            transfer_fee_x: None,
+            transfer_fee_y: None,
+        })
+    }
+
+    // picks up transfer fees from the pool's mints, only needed for Token-2022 mints
+    pub fn with_mints(mut self, mint_x: &[u8], mint_y: &[u8], epoch: u64) -> Result<Self> {
+        self.transfer_fee_x = epoch_transfer_fee(mint_x, epoch)?;
+        self.transfer_fee_y = epoch_transfer_fee(mint_y, epoch)?;
+        Ok(self)
+    }
+
+    pub fn reserves(&self) -> Result<(u64, u64)> {
+        self.config.reserves(self.vault_x, self.vault_y)
+    }
+
+    // mirrors the swap instruction, `now` only matters for dynamic fees
+    pub fn quote_swap(&self, is_x: bool, amount_in: u64, now: i64) -> Result<SwapQuote> {
+        self.config.check_tradable()?;
+        let (x, y) = self.reserves()?;
+        let (fee_in, fee_out) = self.transfer_fees(is_x);
+        let received = amount_received(fee_in, amount_in)?;
+        let res = self.config.clone().swap(now, x, y, is_x, received)?;
+        Ok(SwapQuote {
+            amount_in,
+            amount_out: amount_received(fee_out, res.withdraw)?,
+            fee: res.fee,
+            price_impact_bps: price_impact(x, y, is_x, &res),
+        })
+    }
+
+    // mirrors swap_exact_out, `amount_in` is what max_in has to cover
+    pub fn quote_swap_exact_out(&self, is_x: bool, amount_out: u64, now: i64) -> Result<SwapQuote> {
+        self.config.check_tradable()?;
+        let (x, y) = self.reserves()?;
+        let (fee_in, fee_out) = self.transfer_fees(is_x);
+        let withdraw = amount_to_send(fee_out, amount_out)?;
+        let res = self.config.clone().swap_exact_out(now, x, y, is_x, withdraw)?;
+        Ok(SwapQuote {
+            amount_in: amount_to_send(fee_in, res.deposit)?,
+            amount_out,
+            fee: res.fee,
+            price_impact_bps: price_impact(x, y, is_x, &res),
+        })
+    }
+
+    // mirrors deposit on a funded pool, the amounts are what max_x and max_y have to cover
+    pub fn quote_deposit(&self, lp_amount: u64) -> Result<LiquidityQuote> {
+        self.config.check_tradable()?;
+        if self.lp_supply == 0 {
+            return Err(AmmError::EmptyPool.into());
+        }
+        let (x, y) = self.reserves()?;
+        let amounts = self.config.deposit_amounts(x, y, self.lp_supply, lp_amount)?;
+        Ok(LiquidityQuote {
+            lp_amount,
+            amount_x: amount_to_send(self.transfer_fee_x.as_ref(), amounts.x)?,
+            amount_y: amount_to_send(self.transfer_fee_y.as_ref(), amounts.y)?,
+        })
+    }
+
+    // mirrors the first deposit into an empty pool, MINIMUM_LIQUIDITY stays locked
+    pub fn quote_initial_deposit(&self, amount_x: u64, amount_y: u64) -> Result<LiquidityQuote> {
+        let liquidity = isqrt(
+            (amount_received(self.transfer_fee_x.as_ref(), amount_x)? as u128) *
+                (amount_received(self.transfer_fee_y.as_ref(), amount_y)? as u128)
+        ) as u64;
+        if liquidity <= MINIMUM_LIQUIDITY {
+            return Err(AmmError::InsufficientInitialLiquidity.into());
+        }
+        Ok(LiquidityQuote { lp_amount: liquidity - MINIMUM_LIQUIDITY, amount_x, amount_y })
+    }
+
+    // mirrors withdraw
+    pub fn quote_withdraw(&self, lp_amount: u64) -> Result<LiquidityQuote> {
+        self.config.check_tradable()?;
+        let (x, y) = self.reserves()?;
+        let amounts = self.config.withdraw_amounts(x, y, self.lp_supply, lp_amount)?;
+        Ok(LiquidityQuote {
+            lp_amount,
+            amount_x: amount_received(self.transfer_fee_x.as_ref(), amounts.x)?,
+            amount_y: amount_received(self.transfer_fee_y.as_ref(), amounts.y)?,
+        })
+    }
+
+    // (input, output) transfer fees for a swap in the given direction
+    fn transfer_fees(&self, is_x: bool) -> (Option<&TransferFee>, Option<&TransferFee>) {
+        match is_x {
+            true => (self.transfer_fee_x.as_ref(), self.transfer_fee_y.as_ref()),
+            false => (self.transfer_fee_y.as_ref(), self.transfer_fee_x.as_ref()),
+        }
+    }
+}
+
+fn epoch_transfer_fee(mint: &[u8], epoch: u64) -> Result<Option<TransferFee>> {
+    let state = StateWithExtensions::<MintState>::unpack(mint)?;
+    Ok(
+        state
+            .get_extension::<TransferFeeConfig>()
+            .ok()
+            .map(|config| *config.get_epoch_fee(epoch))
+    )
+}
+
+// same as utils::amount_received in the program
+fn amount_received(fee: Option<&TransferFee>, amount: u64) -> Result<u64> {
+    match fee {
+        Some(fee) =>
+            Ok(amount - fee.calculate_fee(amount).ok_or(ProgramError::ArithmeticOverflow)?),
+        None => Ok(amount),
+    }
+}
+
+// same as utils::amount_to_send in the program
+fn amount_to_send(fee: Option<&TransferFee>, amount: u64) -> Result<u64> {
+    match fee {
+        Some(fee) =>
+            Ok(fee.calculate_pre_fee_amount(amount).ok_or(ProgramError::ArithmeticOverflow)?),
+        None => Ok(amount),
+    }
+}
+
+fn price_impact(x: u64, y: u64, is_x: bool, res: &SwapResult) -> u64 {
+    let (x1, y1) = post_swap_reserves(x, y, is_x, res);
+    price_move_bps(x, y, x1, y1)
+}
@@ -0,0 +1,209 @@
+use amm::{ curve::{ CurveType, MAX_AMP }, state::Config };
+use amm_sdk::Pool;
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::TransferFee;
+use constant_product_curve::{ ConstantProduct, LiquidityPair };
+use proptest::prelude::*;
+
+fn pool(x: u64, y: u64, supply: u64, fee: u16, protocol_fees: (u64, u64)) -> Pool {
+    Pool {
+        config: Config {
+            authority: None,
+            seed: 0,
+            fee,
+            curve: CurveType::ConstantProduct,
+            protocol_fee: 2_000,
+            referral_fee: 0,
+            protocol_fees_x: protocol_fees.0,
+            protocol_fees_y: protocol_fees.1,
+            mint_x: Pubkey::new_unique(),
+            mint_y: Pubkey::new_unique(),
+            locked: false,
+            flash_loan: None,
+            dynamic_fee: None,
+            allowlist: None,
+            allowlist_entries: 0,
+            has_farm: false,
+            price_band: None,
+            config_bump: 255,
+            lp_bump: 255,
+        },
+        // the vaults also hold the protocol's share, which is not LP liquidity
+        vault_x: x + protocol_fees.0,
+        vault_y: y + protocol_fees.1,
+        lp_supply: supply,
+        transfer_fee_x: None,
+        transfer_fee_y: None,
+    }
+}
+
+fn stable_pool(amp: u64, x: u64, y: u64, fee: u16, protocol_fees: (u64, u64)) -> Pool {
+    let mut pool = pool(x, y, 1, fee, protocol_fees);
+    pool.config.curve = CurveType::StableSwap { amp };
+    pool
+}
+
+fn transfer_fee(bps: u16) -> TransferFee {
+    TransferFee {
+        epoch: 0.into(),
+        maximum_fee: u64::MAX.into(),
+        transfer_fee_basis_points: bps.into(),
+    }
+}
+
+fn pair(is_x: bool) -> LiquidityPair {
+    match is_x {
+        true => LiquidityPair::X,
+        false => LiquidityPair::Y,
+    }
+}
+
+proptest! {
+    #[test]
+    fn swap_matches_constant_product(
+        x in 1_000u64..1_000_000_000_000_000,
+        y in 1_000u64..1_000_000_000_000_000,
+        amount in 1u64..1_000_000_000_000,
+        fee in 0u16..1_000,
+        protocol_fees in (0u64..1_000_000, 0u64..1_000_000),
+        is_x: bool,
+    ) {
+        let quote = pool(x, y, 1, fee, protocol_fees).quote_swap(is_x, amount, 0);
+        let expected = ConstantProduct::init(x, y, x, fee, None)
+            .and_then(|mut curve| curve.swap(pair(is_x), amount, 0));
+        match (quote, expected) {
+            (Ok(quote), Ok(expected)) => {
+                prop_assert_eq!(quote.amount_in, expected.deposit);
+                prop_assert_eq!(quote.amount_out, expected.withdraw);
+                prop_assert_eq!(quote.fee, expected.fee);
+            }
+            (quote, expected) => prop_assert_eq!(quote.is_ok(), expected.is_ok()),
+        }
+    }
+
+    #[test]
+    fn swap_exact_out_buys_at_least_amount_out(
+        x in 1_000_000u64..1_000_000_000_000_000,
+        y in 1_000_000u64..1_000_000_000_000_000,
+        share in 1u64..500,
+        fee in 0u16..1_000,
+        is_x: bool,
+    ) {
+        let reserve_out = if is_x { y } else { x };
+        let amount_out = (reserve_out / 1_000 * share).max(1);
+        let pool = pool(x, y, 1, fee, (0, 0));
+        let quote = pool.quote_swap_exact_out(is_x, amount_out, 0).unwrap();
+        let expected = ConstantProduct::init(x, y, x, fee, None)
+            .and_then(|mut curve| curve.swap(pair(is_x), quote.amount_in, 0))
+            .unwrap();
+        prop_assert!(expected.withdraw >= amount_out);
+        prop_assert_eq!(quote.fee, expected.fee);
+    }
+
+    #[test]
+    fn deposit_and_withdraw_are_proportional(
+        x in 1_000u64..1_000_000_000_000_000,
+        y in 1_000u64..1_000_000_000_000_000,
+        supply in 1_000u64..1_000_000_000_000_000,
+        amount in 1u64..1_000_000_000_000,
+    ) {
+        let pool = pool(x, y, supply, 30, (7, 11));
+
+        // proportional to the reserves, rounded up going in and down coming out
+        let deposit = pool.quote_deposit(amount).unwrap();
+        let share = |reserve: u64| (reserve as u128) * (amount as u128);
+        prop_assert_eq!(deposit.amount_x as u128, share(x).div_ceil(supply as u128));
+        prop_assert_eq!(deposit.amount_y as u128, share(y).div_ceil(supply as u128));
+
+        let amount = amount.min(supply);
+        let share = |reserve: u64| (reserve as u128) * (amount as u128);
+        let withdraw = pool.quote_withdraw(amount).unwrap();
+        prop_assert_eq!(withdraw.amount_x as u128, share(x) / (supply as u128));
+        prop_assert_eq!(withdraw.amount_y as u128, share(y) / (supply as u128));
+    }
+
+    #[test]
+    fn transfer_fees_apply_before_and_after_the_curve(
+        x in 1_000_000u64..1_000_000_000_000,
+        y in 1_000_000u64..1_000_000_000_000,
+        amount in 1_000u64..1_000_000_000,
+        bps_in in 0u16..500,
+        bps_out in 0u16..500,
+    ) {
+        let mut pool = pool(x, y, 1, 30, (0, 0));
+        pool.transfer_fee_x = Some(transfer_fee(bps_in));
+        pool.transfer_fee_y = Some(transfer_fee(bps_out));
+        let quote = pool.quote_swap(true, amount, 0).unwrap();
+
+        let received = amount - transfer_fee(bps_in).calculate_fee(amount).unwrap();
+        let expected = ConstantProduct::init(x, y, x, 30, None)
+            .and_then(|mut curve| curve.swap(LiquidityPair::X, received, 0))
+            .unwrap();
+        let amount_out = expected.withdraw - transfer_fee(bps_out).calculate_fee(expected.withdraw).unwrap();
+        prop_assert_eq!(quote.amount_out, amount_out);
+    }
+
+    #[test]
+    fn price_impact_grows_with_size(
+        x in 1_000_000u64..1_000_000_000_000,
+        y in 1_000_000u64..1_000_000_000_000,
+        amount in 1_000u64..1_000_000_000,
+    ) {
+        let pool = pool(x, y, 1, 30, (0, 0));
+        let small = pool.quote_swap(true, amount, 0).unwrap();
+        let large = pool.quote_swap(true, amount * 2, 0).unwrap();
+        prop_assert!(large.price_impact_bps >= small.price_impact_bps);
+    }
+
+    #[test]
+    fn stable_swap_quotes_match_the_curve(
+        amp in prop_oneof![Just(1u64), Just(100), Just(MAX_AMP)],
+        x in 1_000_000u64..1_000_000_000_000_000,
+        y in 1_000_000u64..1_000_000_000_000_000,
+        amount in 1u64..1_000_000_000_000,
+        fee in 0u16..1_000,
+        protocol_fees in (0u64..1_000_000, 0u64..1_000_000),
+        is_x: bool,
+    ) {
+        // priced against the reserves net of protocol fees, like the program
+        let curve = CurveType::StableSwap { amp };
+        let quote = stable_pool(amp, x, y, fee, protocol_fees).quote_swap(is_x, amount, 0);
+        match (quote, curve.swap(x, y, fee, is_x, amount)) {
+            (Ok(quote), Ok(expected)) => {
+                prop_assert_eq!(quote.amount_out, expected.withdraw);
+                prop_assert_eq!(quote.fee, expected.fee);
+            }
+            (quote, expected) => prop_assert_eq!(quote.is_ok(), expected.is_ok()),
+        }
+    }
+
+    #[test]
+    fn stable_swap_exact_out_buys_at_least_amount_out(
+        amp in prop_oneof![Just(1u64), Just(100), Just(MAX_AMP)],
+        x in 1_000_000u64..1_000_000_000_000_000,
+        y in 1_000_000u64..1_000_000_000_000_000,
+        share in 1u64..500,
+        is_x: bool,
+    ) {
+        let reserve_out = if is_x { y } else { x };
+        let amount_out = (reserve_out / 1_000 * share).max(1);
+        let quote = stable_pool(amp, x, y, 30, (0, 0)).quote_swap_exact_out(is_x, amount_out, 0).unwrap();
+        let expected = CurveType::StableSwap { amp }.swap(x, y, 30, is_x, quote.amount_in).unwrap();
+        prop_assert!(expected.withdraw >= amount_out);
+        prop_assert_eq!(quote.fee, expected.fee);
+    }
+
+    #[test]
+    fn stable_swap_pays_more_than_constant_product_near_the_peg(
+        amp in prop_oneof![Just(100u64), Just(MAX_AMP)],
+        reserve in 1_000_000_000u64..1_000_000_000_000,
+        share in 1u64..100,
+    ) {
+        // the same trade against a balanced pool, the flatter curve slips less
+        let amount = reserve / 1_000 * share;
+        let stable = stable_pool(amp, reserve, reserve, 30, (0, 0)).quote_swap(true, amount, 0).unwrap();
+        let product = pool(reserve, reserve, 1, 30, (0, 0)).quote_swap(true, amount, 0).unwrap();
+        prop_assert!(stable.amount_out >= product.amount_out);
+        prop_assert!(stable.price_impact_bps > 0);
+    }
+}
@@ -0,0 +1,9 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum EscrowError {
+    #[msg("Fill amount must be between 1 and the amount still owed to the maker")]
+    InvalidFillAmount,
+    #[msg("Fill is too small to release any of the deposit")]
+    FillTooSmall,
+}
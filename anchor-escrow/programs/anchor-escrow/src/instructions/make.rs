@@ -51,6 +51,7 @@ impl<'info> Make<'info> {
             mint_a: self.mint_a.key(),
             mint_b: self.mint_b.key(),
             recieve_amount,
+            deposit_amount: 0,
             bump: bumps.escrow,
         });
         Ok(())
@@ -66,6 +67,9 @@ impl<'info> Make<'info> {
         };
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
         transfer_checked(cpi_ctx, amount, self.mint_a.decimals)?;
+        // record what actually landed, a transfer fee may have kept some of it
+        self.vault.reload()?;
+        self.escrow.deposit_amount = self.vault.amount;
         Ok(())
     }
 }
@@ -1,7 +1,9 @@
 mod make;
 mod take;
+mod take_partial;
 mod refund;
 
 pub use make::*;
 pub use take::*;
+pub use take_partial::*;
 pub use refund::*;
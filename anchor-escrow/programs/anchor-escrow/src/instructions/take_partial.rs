@@ -0,0 +1,147 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        transfer_checked,
+        Mint,
+        TokenAccount,
+        TokenInterface,
+        TransferChecked,
+        close_account,
+        CloseAccount,
+    },
+};
+use crate::{ error::EscrowError, state::Escrow };
+
+// like Take, but the escrow stays open until the last fill
+#[derive(Accounts)]
+pub struct TakePartial<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(address = escrow.maker)]
+    pub maker: SystemAccount<'info>,
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    #[account(address = escrow.mint_b)]
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
+    )]
+    pub taker_mint_a_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
+    )]
+    pub taker_mint_b_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_mint_b_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = mint_b,
+        has_one = mint_a,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> TakePartial<'info> {
+    // mint_a released for `amount_b`, pro rata to what is left, rounded down in the maker's
+    // favour. The last fill takes the whole vault, so tokens sent to it directly can't keep
+    // it from being closed
+    pub fn fill(&mut self, amount_b: u64) -> Result<u64> {
+        let escrow = &mut self.escrow;
+        require!(
+            amount_b > 0 && amount_b <= escrow.recieve_amount,
+            EscrowError::InvalidFillAmount
+        );
+        let amount_a = match amount_b == escrow.recieve_amount {
+            true => self.vault.amount,
+            false =>
+                (((escrow.deposit_amount as u128) * (amount_b as u128)) /
+                    (escrow.recieve_amount as u128)) as u64,
+        };
+        require!(amount_a > 0, EscrowError::FillTooSmall);
+
+        escrow.recieve_amount -= amount_b;
+        escrow.deposit_amount = escrow.deposit_amount.saturating_sub(amount_a);
+        Ok(amount_a)
+    }
+
+    pub fn transfer_to_maker(&mut self, amount: u64) -> Result<()> {
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_accounts = TransferChecked {
+            from: self.taker_mint_b_ata.to_account_info(),
+            to: self.maker_mint_b_ata.to_account_info(),
+            authority: self.taker.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer_checked(cpi_ctx, amount, self.mint_b.decimals)?;
+        Ok(())
+    }
+
+    pub fn transfer_to_taker(&mut self, amount: u64) -> Result<()> {
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_accounts = TransferChecked {
+            from: self.vault.to_account_info(),
+            to: self.taker_mint_a_ata.to_account_info(),
+            mint: self.mint_a.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+        let seeds: &[&[&[u8]]] = &[
+            &[
+                b"escrow",
+                self.maker.key.as_ref(),
+                &self.escrow.seed.to_le_bytes(),
+                &[self.escrow.bump],
+            ],
+        ];
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, seeds);
+        transfer_checked(cpi_ctx, amount, self.mint_a.decimals)?;
+        Ok(())
+    }
+
+    // only once fully filled; rent goes to the taker of the last fill, as with take
+    pub fn close_vault_and_escrow(&mut self) -> Result<()> {
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_account = CloseAccount {
+            account: self.vault.to_account_info(),
+            destination: self.taker.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+        let seeds: &[&[&[u8]]] = &[
+            &[
+                b"escrow",
+                self.maker.key.as_ref(),
+                &self.escrow.seed.to_le_bytes(),
+                &[self.escrow.bump],
+            ],
+        ];
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_account, seeds);
+        close_account(cpi_ctx)?;
+        self.escrow.close(self.taker.to_account_info())
+    }
+}
@@ -1,5 +1,6 @@
 use anchor_lang::prelude::*;
 
+mod error;
 mod state;
 mod instructions;
 use crate::instructions::*;
@@ -29,6 +30,16 @@ pub mod anchor_escrow {
         Ok(())
     }
 
+    pub fn take_partial(ctx: Context<TakePartial>, amount_b: u64) -> Result<()> {
+        let amount_a = ctx.accounts.fill(amount_b)?;
+        ctx.accounts.transfer_to_maker(amount_b)?;
+        ctx.accounts.transfer_to_taker(amount_a)?;
+        if ctx.accounts.escrow.recieve_amount == 0 {
+            ctx.accounts.close_vault_and_escrow()?;
+        }
+        Ok(())
+    }
+
     pub fn refund(ctx: Context<Refund>) -> Result<()> {
         ctx.accounts.refund_and_close_vault()
     }
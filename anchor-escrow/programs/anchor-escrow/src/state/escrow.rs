@@ -7,6 +7,9 @@ pub struct Escrow {
     pub maker: Pubkey,
     pub mint_a: Pubkey, 
     pub mint_b: Pubkey, 
+    // what the maker is still owed in mint_b, goes down with every partial fill
     pub recieve_amount: u64,
+    // what is still left in the vault for takers
+    pub deposit_amount: u64,
     pub bump: u8,
 }
\ No newline at end of file
@@ -1,16 +1,173 @@
 import * as anchor from "@coral-xyz/anchor";
-import { Program } from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
 import { AnchorEscrow } from "../target/types/anchor_escrow";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createAssociatedTokenAccount,
+  createMint,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  mintTo,
+  transfer,
+} from "@solana/spl-token";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import NodeWallet from "@coral-xyz/anchor/dist/cjs/nodewallet";
+import { assert } from "chai";
 
 describe("anchor-escrow", () => {
   // Configure the client to use the local cluster.
-  anchor.setProvider(anchor.AnchorProvider.env());
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const connection = provider.connection;
 
   const program = anchor.workspace.AnchorEscrow as Program<AnchorEscrow>;
 
-  it("Is initialized!", async () => {
-    // Add your test here.
-    const tx = await program.methods.initialize().rpc();
-    console.log("Your transaction signature", tx);
+  const maker = (provider.wallet as NodeWallet).payer;
+  const takers = [Keypair.generate(), Keypair.generate()];
+  let mintA: PublicKey;
+  let mintB: PublicKey;
+
+  const ata = (mint: PublicKey, owner: PublicKey) =>
+    getAssociatedTokenAddressSync(mint, owner, true);
+
+  const balance = async (mint: PublicKey, owner: PublicKey) =>
+    Number((await getAccount(connection, ata(mint, owner))).amount);
+
+  const make = async (seed: number, receive: number, deposit: number) => {
+    const [escrow] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), Buffer.from([seed])],
+      program.programId
+    );
+    await program.methods
+      .make(seed, new BN(receive), new BN(deposit))
+      .accountsPartial({
+        maker: maker.publicKey,
+        mintA,
+        mintB,
+        makerMintAAta: ata(mintA, maker.publicKey),
+        escrow,
+        vault: ata(mintA, escrow),
+        systemProgram: SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    return escrow;
+  };
+
+  const takePartial = (escrow: PublicKey, taker: Keypair, amountB: number) =>
+    program.methods
+      .takePartial(new BN(amountB))
+      .accountsPartial({
+        taker: taker.publicKey,
+        maker: maker.publicKey,
+        mintA,
+        mintB,
+        takerMintAAta: ata(mintA, taker.publicKey),
+        takerMintBAta: ata(mintB, taker.publicKey),
+        makerMintBAta: ata(mintB, maker.publicKey),
+        escrow,
+        vault: ata(mintA, escrow),
+        systemProgram: SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([taker])
+      .rpc();
+
+  const expectError = async (tx: Promise<string>, code: string) => {
+    try {
+      await tx;
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, code);
+      return;
+    }
+    assert.fail(`expected ${code}`);
+  };
+
+  before(async () => {
+    mintA = await createMint(connection, maker, maker.publicKey, null, 6);
+    mintB = await createMint(connection, maker, maker.publicKey, null, 6);
+    const makerAtaA = await createAssociatedTokenAccount(
+      connection,
+      maker,
+      mintA,
+      maker.publicKey
+    );
+    await mintTo(connection, maker, mintA, makerAtaA, maker, 1_000_000);
+
+    for (const taker of takers) {
+      await connection.confirmTransaction(
+        await connection.requestAirdrop(
+          taker.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        )
+      );
+      const takerAtaB = await createAssociatedTokenAccount(
+        connection,
+        maker,
+        mintB,
+        taker.publicKey
+      );
+      await mintTo(connection, maker, mintB, takerAtaB, maker, 1_000_000);
+    }
+  });
+
+  it("fills in several steps and closes on the last fill", async () => {
+    const escrow = await make(1, 300, 900);
+
+    await takePartial(escrow, takers[0], 100);
+    assert.equal(await balance(mintA, takers[0].publicKey), 300);
+    const state = await program.account.escrow.fetch(escrow);
+    assert.equal(state.recieveAmount.toNumber(), 200);
+    assert.equal(state.depositAmount.toNumber(), 600);
+
+    // tokens sent straight to the vault go to the last taker instead of blocking the close
+    await transfer(
+      connection,
+      maker,
+      ata(mintA, maker.publicKey),
+      ata(mintA, escrow),
+      maker,
+      1
+    );
+    const remainder = await balance(mintA, escrow);
+    assert.equal(remainder, 601);
+    await takePartial(escrow, takers[1], 200);
+    assert.equal(await balance(mintA, takers[1].publicKey), remainder);
+    assert.equal(await balance(mintB, maker.publicKey), 300);
+    assert.isNull(await program.account.escrow.fetchNullable(escrow));
+    assert.isNull(await connection.getAccountInfo(ata(mintA, escrow)));
+  });
+
+  it("rounds partial fills in the maker's favour", async () => {
+    const escrow = await make(2, 3, 10);
+    const before = await balance(mintA, takers[0].publicKey);
+
+    // 10 / 3 per unit of mint B, rounded down; the remainder goes with the last fill
+    await takePartial(escrow, takers[0], 1);
+    assert.equal(await balance(mintA, takers[0].publicKey) - before, 3);
+    await takePartial(escrow, takers[0], 1);
+    assert.equal(await balance(mintA, takers[0].publicKey) - before, 6);
+    await takePartial(escrow, takers[0], 1);
+    assert.equal(await balance(mintA, takers[0].publicKey) - before, 10);
+    assert.isNull(await program.account.escrow.fetchNullable(escrow));
+  });
+
+  it("rejects fills that are empty, too large or too small", async () => {
+    const escrow = await make(3, 1_000, 10);
+
+    await expectError(takePartial(escrow, takers[0], 0), "InvalidFillAmount");
+    await expectError(
+      takePartial(escrow, takers[0], 1_001),
+      "InvalidFillAmount"
+    );
+    // 10 * 1 / 1000 rounds down to nothing
+    await expectError(takePartial(escrow, takers[0], 1), "FillTooSmall");
+
+    const state = await program.account.escrow.fetch(escrow);
+    assert.equal(state.recieveAmount.toNumber(), 1_000);
+    assert.equal(state.depositAmount.toNumber(), 10);
   });
 });